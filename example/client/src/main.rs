@@ -1,4 +1,4 @@
-use rusty_socket_client::SocketClient;
+use rusty_socket_client::{Message, SocketClient};
 use std::io::{self, Write};
 mod commands;
 use commands::Commands;
@@ -48,7 +48,10 @@ fn main() {
                                 // screen_init(true);
 
                                 if let Some(ref mut connected_client) = socket_client {
-                                    let receive_func = |message: String| {
+                                    let receive_func = |message: Message| {
+                                        if let Message::Ping(_) | Message::Pong(_) = message {
+                                            return;
+                                        }
                                         println!("rcv=> {}", message);
                                         print!("msg> ");
                                         io::stdout().flush().unwrap();
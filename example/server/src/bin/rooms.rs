@@ -0,0 +1,71 @@
+use rusty_socket_server::{CloseFrame, Connection, Handler, Message, Router, ServerConfig, SocketServer};
+
+// Chat with rooms: clients connect to `/chat/<room>`, or `/` for the lobby.
+// `/join <room>` and `/leave <room>` change rooms, `/rooms` lists them, other
+// text goes to everyone in the sender's rooms.
+struct RoomChat;
+
+impl RoomChat {
+    fn announce(connection: &Connection, room: &str, text: &str) {
+        let text = format!("[{}] {} {}", room, connection.peer_addr(), text);
+        connection.registry().publish_except(room, connection.id(), text.as_str());
+    }
+
+    fn command(connection: &Connection, text: &str) {
+        let (command, room) = text.split_once(' ').unwrap_or((text, ""));
+        let room = room.trim();
+        let reply = match command {
+            "/join" if !room.is_empty() => {
+                connection.join(room);
+                Self::announce(connection, room, "joined");
+                format!("joined {}", room)
+            }
+            "/leave" if connection.leave(room) => {
+                Self::announce(connection, room, "left");
+                format!("left {}", room)
+            }
+            "/rooms" => format!("your rooms: {}", connection.rooms().join(", ")),
+            _ => String::from("commands: /join <room>, /leave <room>, /rooms"),
+        };
+        let _ = connection.send(reply.as_str());
+    }
+}
+
+impl Handler for RoomChat {
+    fn on_open(&self, connection: &Connection) {
+        let room = connection.route().param("room").unwrap_or("lobby").to_string();
+        connection.join(&room);
+        Self::announce(connection, &room, "joined");
+        let _ = connection.send(format!("welcome to {}", room).as_str());
+    }
+
+    fn on_message(&self, connection: &Connection, message: Message) {
+        let text = match message {
+            Message::Text(text) => text,
+            _ => return,
+        };
+        if text.starts_with('/') {
+            return Self::command(connection, &text);
+        }
+
+        for room in connection.rooms() {
+            let text = format!("[{}] {}: {}", room, connection.peer_addr(), text);
+            connection.registry().publish_except(&room, connection.id(), text.as_str());
+        }
+    }
+
+    // the server takes the connection out of its rooms once this returns
+    fn on_close(&self, connection: &Connection, _close_frame: Option<CloseFrame>) {
+        for room in connection.rooms() {
+            Self::announce(connection, &room, "left");
+        }
+    }
+}
+
+fn main() {
+    let router = Router::new().route("/chat/:room", RoomChat).fallback(RoomChat);
+    match SocketServer::build_with_router("127.0.0.1:8080", router, ServerConfig::default()) {
+        Ok(server) => server.start(),
+        Err(e) => println!("Error: {}", e),
+    }
+}
@@ -1,7 +1,7 @@
-use rusty_socket_server::SocketServer;
+use rusty_socket_server::{BroadcastHandler, SocketServer};
 
 fn main() {
-    match SocketServer::build("127.0.0.1:8080") {
+    match SocketServer::build("127.0.0.1:8080", BroadcastHandler::new()) {
        Ok(server) => {
            server.start();
        },
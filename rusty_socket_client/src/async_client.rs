@@ -0,0 +1,112 @@
+use crate::ClientConfig;
+use crate::ScError;
+use crate::Result;
+use crate::WebSocketUrl;
+use crate::utils;
+
+use rusty_socket_core::{
+    AsyncReceiver, AsyncSender, AsyncSocket, AsyncStream, CloseCode, CloseFrame, ConnectionStatus, Message,
+    Role,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+pub struct AsyncSocketClient {
+    socket: AsyncSocket<AsyncStream>,
+}
+
+impl AsyncSocketClient {
+    pub async fn connect(url: &str) -> Result<Self> {
+        Self::connect_with_config(url, ClientConfig::default()).await
+    }
+
+    pub async fn connect_with_config(url: &str, config: ClientConfig) -> Result<Self> {
+        let parsed_url = WebSocketUrl::from_url(url)?;
+        let stream = TcpStream::connect(parsed_url.address()?).await?;
+        let mut stream = Self::wrap_stream(stream, &parsed_url, &config).await?;
+
+        let websocket_key = utils::generate_key();
+        let websocket_request = utils::handshake_request(&parsed_url, &websocket_key, &config);
+        stream.write_all(websocket_request.as_bytes()).await?;
+        stream.flush().await?;
+
+        let mut response = Vec::new();
+        let mut buffer = [0u8; 512];
+        let head_end = loop {
+            if let Some(head_end) = utils::head_end(&response) {
+                break head_end;
+            }
+            if response.len() > utils::MAX_HANDSHAKE_SIZE {
+                return Err(ScError::InvalidHttpResponse);
+            }
+
+            match stream.read(&mut buffer).await? {
+                0 => return Err(ScError::ServerClosed),
+                size => response.extend_from_slice(&buffer[..size]),
+            }
+        };
+        let resp_headers =
+            utils::verify_handshake_response(&String::from_utf8_lossy(&response[..head_end]), &websocket_key)?;
+        let extensions = utils::negotiate_extensions(&config, &resp_headers)?;
+        let protocol = utils::negotiate_protocol(&config, &resp_headers)?;
+
+        let socket = AsyncSocket::new(stream, Role::Client)
+            .buffered(&response[head_end..])
+            .fragment_size(config.fragment_size)
+            .close_timeout(config.close_timeout)
+            .max_frame_size(config.max_frame_size)
+            .max_message_size(config.max_message_size)
+            .heartbeat(config.heartbeat_interval, config.heartbeat_timeout)
+            .extensions(extensions)
+            .with_protocol(protocol);
+
+        Ok(AsyncSocketClient { socket })
+    }
+
+    async fn wrap_stream(stream: TcpStream, url: &WebSocketUrl, config: &ClientConfig) -> Result<AsyncStream> {
+        if !url.is_secure() {
+            return Ok(AsyncStream::Plain(stream));
+        }
+
+        #[cfg(feature = "tls")]
+        {
+            Ok(AsyncStream::connect_tls(config.tls_config()?, url.host_name(), stream).await?)
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            let _ = config;
+            Err(ScError::TlsNotEnabled)
+        }
+    }
+
+    // subprotocol the server picked from `ClientConfig::protocols`, if any
+    pub fn protocol(&self) -> Option<&str> {
+        self.socket.protocol()
+    }
+
+    pub fn status(&self) -> ConnectionStatus {
+        self.socket.status()
+    }
+
+    // Next message from the server, `None` once the connection is closed.
+    pub async fn next(&mut self) -> Option<Result<Message>> {
+        self.socket.next().await.map(|message| message.map_err(ScError::from))
+    }
+
+    pub async fn send<M: Into<Message>>(&self, message: M) -> Result<()> {
+        Ok(self.socket.send(message).await?)
+    }
+
+    pub async fn close(&mut self) -> Result<Option<CloseFrame>> {
+        self.close_with(CloseFrame::new(CloseCode::Normal, "")).await
+    }
+
+    pub async fn close_with(&mut self, close_frame: CloseFrame) -> Result<Option<CloseFrame>> {
+        Ok(self.socket.close(close_frame).await?)
+    }
+
+    // Sending and receiving halves for driving the connection from separate tasks.
+    pub fn split(self) -> (AsyncSender<AsyncStream>, AsyncReceiver<AsyncStream>) {
+        self.socket.split()
+    }
+}
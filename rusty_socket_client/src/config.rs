@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use rusty_socket_core::{DeflateConfig, Extension, ExtensionFactory, PerMessageDeflate, Role};
+
+#[cfg(feature = "tls")]
+use std::{io, sync::Arc};
+
+pub struct ClientConfig {
+    // frame size for what we send, `None` sends each message in one frame
+    pub fragment_size: Option<usize>,
+    // limit for frames from the server, see `FrameDecoder`
+    pub max_frame_size: usize,
+    // limit for messages from the server, see `MessageAssembler`
+    pub max_message_size: usize,
+    // how long `close` waits for the server to answer our close frame
+    pub close_timeout: Duration,
+    // send a ping this often while receiving, `None` turns heartbeats off
+    pub heartbeat_interval: Option<Duration>,
+    // how long the server may leave our ping unanswered
+    pub heartbeat_timeout: Duration,
+    // offer permessage-deflate with these parameters, `None` asks for no compression
+    pub deflate: Option<DeflateConfig>,
+    // extensions to offer besides permessage-deflate
+    pub extensions: Vec<ExtensionFactory>,
+    // subprotocols to offer, most preferred first, the server must pick one of them
+    pub protocols: Vec<String>,
+    // TLS settings for `wss://`, `None` verifies servers against the bundled web roots
+    #[cfg(feature = "tls")]
+    pub tls: Option<Arc<rustls::ClientConfig>>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            fragment_size: None,
+            max_frame_size: 16 << 20,
+            max_message_size: 64 << 20,
+            close_timeout: Duration::from_secs(5),
+            heartbeat_interval: None,
+            heartbeat_timeout: Duration::from_secs(10),
+            deflate: None,
+            extensions: Vec::new(),
+            protocols: Vec::new(),
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    // Fresh instances of every extension we offer, in order of preference.
+    pub(crate) fn extensions(&self) -> Vec<Box<dyn Extension>> {
+        let mut extensions: Vec<Box<dyn Extension>> = Vec::new();
+        if let Some(deflate) = self.deflate {
+            extensions.push(Box::new(PerMessageDeflate::new(deflate, Role::Client)));
+        }
+        extensions.extend(self.extensions.iter().map(|factory| factory()));
+
+        extensions
+    }
+
+    pub fn with_protocols<P: Into<String>>(mut self, protocols: impl IntoIterator<Item = P>) -> Self {
+        self.protocols = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+#[cfg(feature = "tls")]
+impl ClientConfig {
+    // Verifies servers against these roots only, e.g. a private CA or a self-signed certificate.
+    pub fn with_root_certificates(mut self, roots: rustls::RootCertStore) -> crate::Result<Self> {
+        self.tls = Some(Self::build_tls(roots)?);
+        Ok(self)
+    }
+
+    pub(crate) fn tls_config(&self) -> crate::Result<Arc<rustls::ClientConfig>> {
+        match &self.tls {
+            Some(tls_config) => Ok(Arc::clone(tls_config)),
+            None => Self::build_tls(rustls::RootCertStore {
+                roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+            }),
+        }
+    }
+
+    fn build_tls(roots: rustls::RootCertStore) -> crate::Result<Arc<rustls::ClientConfig>> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let tls_config = rustls::ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        Ok(Arc::new(tls_config))
+    }
+}
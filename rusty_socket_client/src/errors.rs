@@ -1,6 +1,8 @@
 use std::fmt;
 use std::io;
 
+use rusty_socket_core::RsError;
+
 #[derive(Debug)]
 pub enum ScError {
     InvalidUrl,
@@ -11,6 +13,9 @@ pub enum ScError {
     LowerHttpVersion,
     InvalidHandshakeHeader,
     DataFrameError,
+    ProtocolError(RsError),
+    TlsNotEnabled,
+    ProtocolNotOffered,
 }
 
 impl PartialEq for ScError {
@@ -23,7 +28,10 @@ impl PartialEq for ScError {
             (ScError::InvalidHttpResponse, ScError::InvalidHttpResponse )=> true,
             (ScError::InvalidHandshakeHeader, ScError::InvalidHandshakeHeader )=> true,
             (ScError::DataFrameError, ScError::DataFrameError )=> true,
+            (ScError::TlsNotEnabled, ScError::TlsNotEnabled )=> true,
+            (ScError::ProtocolNotOffered, ScError::ProtocolNotOffered )=> true,
             (ScError::IoError(e1), ScError::IoError(e2)) => e1.kind() == e2.kind(),
+            (ScError::ProtocolError(e1), ScError::ProtocolError(e2)) => e1.to_string() == e2.to_string(),
             _ => false,
         }
     }
@@ -40,6 +48,9 @@ impl fmt::Display for ScError{
             Self::InvalidHttpResponse => write!(f, "Invalid http response line"),
             Self::InvalidHandshakeHeader => write!(f, "Handshake response header invalid."),
             Self::DataFrameError => write!(f, "Failed to create dataframe"),
+            Self::ProtocolError(e) => write!(f, "Protocol error: {}", e),
+            Self::TlsNotEnabled => write!(f, "wss:// urls need the tls feature"),
+            Self::ProtocolNotOffered => write!(f, "Server did not pick one of the offered subprotocols"),
         }
     }
 }
@@ -50,4 +61,10 @@ impl From<io::Error> for ScError {
     }
 }
 
+impl From<RsError> for ScError {
+    fn from(error: RsError) -> Self {
+        ScError::ProtocolError(error)
+    }
+}
+
 impl std::error::Error for ScError {}
\ No newline at end of file
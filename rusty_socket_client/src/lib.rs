@@ -1,10 +1,21 @@
+#[cfg(feature = "tokio")]
+pub mod async_client;
+pub mod config;
 pub mod socket_client;
 pub mod errors;
 pub mod url;
 pub mod utils;
 
 pub use socket_client::SocketClient;
+#[cfg(feature = "tokio")]
+pub use async_client::AsyncSocketClient;
+#[cfg(feature = "tokio")]
+pub use rusty_socket_core::{AsyncReceiver, AsyncSender, AsyncStream};
+pub use config::ClientConfig;
 pub use errors::ScError;
 pub use url::WebSocketUrl;
+pub use rusty_socket_core::{CloseCode, CloseFrame, Message};
+#[cfg(feature = "tls")]
+pub use rusty_socket_core::rustls;
 
 pub type Result<T> = std::result::Result<T, ScError>;
\ No newline at end of file
@@ -1,32 +1,82 @@
+use crate::ClientConfig;
 use crate::ScError;
 use crate::Result;
 use crate::WebSocketUrl;
 use crate::utils;
 
-use base64::encode;
-use rand::RngCore;
-
-use std::io::{Write, Read};
+use std::io::{ErrorKind, Write, Read};
 use std::net::{Shutdown, TcpStream};
 use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
-use rusty_socket_core::{DataFrame, OpCode};
+use std::time::Instant;
+use rusty_socket_core::{
+    CloseCode, CloseFrame, ConnectionStatus, DataFrame, Extensions, FrameDecoder,
+    Heartbeat, HeartbeatAction, Message, MessageAssembler, OpCode, Role, Transport,
+};
+#[cfg(feature = "tls")]
+use rusty_socket_core::TlsStream;
+
+struct CloseState {
+    status: ConnectionStatus,
+    peer_close: Option<CloseFrame>,
+    on_close: Option<Box<dyn Fn(Option<CloseFrame>) + Send>>,
+}
+
+type SharedCloseState = Arc<(Mutex<CloseState>, Condvar)>;
+
+type SharedStream = Arc<Mutex<Box<dyn Transport>>>;
+
+// stream, agreed extensions and subprotocol, and any bytes read past the response
+type Handshake = (Box<dyn Transport>, Extensions, Option<String>, Vec<u8>);
 
 pub struct SocketClient {
-    pub stream: TcpStream,
+    pub stream: Box<dyn Transport>,
+    writer: SharedStream,
+    config: ClientConfig,
+    close_state: SharedCloseState,
+    receiving: bool,
+    extensions: Arc<Mutex<Extensions>>,
+    reserved_bits: u8,
+    protocol: Option<String>,
+    // frames that arrived together with the handshake response
+    buffered: Vec<u8>,
 }
 
 impl SocketClient{
     pub fn build(url: &str) -> Result<Self> {
+        Self::build_with_config(url, ClientConfig::default())
+    }
+
+    pub fn build_with_config(url: &str, config: ClientConfig) -> Result<Self> {
         let result_url = WebSocketUrl::from_url(url);
 
         match result_url {
             Ok(parsed_url) => {
                 match TcpStream::connect(&parsed_url){
                     Ok(stream) => {
-                        let frame_stream = Self::perform_handshake(stream, parsed_url)?;
+                        let stream = Self::wrap_transport(stream, &parsed_url, &config)?;
+                        let (frame_stream, extensions, protocol, buffered) =
+                            Self::perform_handshake(stream, parsed_url, &config)?;
+                        let writer = frame_stream.try_clone_boxed().map_err(ScError::from)?;
+
+                        let close_state = CloseState {
+                            status: ConnectionStatus::Open,
+                            peer_close: None,
+                            on_close: None,
+                        };
 
-                        Ok(SocketClient{stream: frame_stream})
+                        Ok(SocketClient{
+                            stream: frame_stream,
+                            writer: Arc::new(Mutex::new(writer)),
+                            config,
+                            close_state: Arc::new((Mutex::new(close_state), Condvar::new())),
+                            receiving: false,
+                            reserved_bits: extensions.rsv_bits(),
+                            extensions: Arc::new(Mutex::new(extensions)),
+                            protocol,
+                            buffered,
+                        })
                     },
                     Err(e) => Err(ScError::from(e)),
                 }
@@ -35,128 +85,349 @@ impl SocketClient{
         }
     }
 
-    pub fn send(&mut self, message: &str) -> Result<()> {
-        if message.len() == 0 {
-            return Err(ScError::DataFrameError);
+    // subprotocol the server picked from `ClientConfig::protocols`, if any
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    pub fn send<M: Into<Message>>(&mut self, message: M) -> Result<()> {
+        let message: Message = message.into();
+        if let Message::Close(close_frame) = message {
+            let close_frame = close_frame.unwrap_or_else(|| CloseFrame::new(CloseCode::Normal, ""));
+            return self.close_with(close_frame).map(|_| ());
         }
-        match DataFrame::from_data(message, OpCode::Text, true) {
-            Some(frame) => {
-                self.stream.write_all(&Vec::from(frame)).map_err(ScError::from)?;
-                self.stream.flush().map_err(ScError::from)?;
 
-                Ok(())
-            },
-            None => Err(ScError::DataFrameError),
+        let fragment_size = self.config.fragment_size.unwrap_or(0);
+        let frames = {
+            let mut extensions = self.extensions.lock().unwrap();
+            if extensions.is_empty() || message.is_control() {
+                None
+            } else {
+                Some(extensions.to_frames(&message, true, fragment_size)?)
+            }
+        };
+
+        match frames {
+            Some(frames) => Self::write_frames(&self.writer, frames),
+            None => {
+                // masked chunk by chunk on the way out, the payload is not copied
+                let mut stream = self.writer.lock().unwrap();
+                message.encode_into(&mut *stream, true, fragment_size)?;
+                stream.flush().map_err(ScError::from)
+            }
         }
     }
 
     pub fn on_receive<F>(&mut self, receive_func: F ) -> Result<()>
     where
-        F: Fn(String) + Send + 'static
+        F: Fn(Message) + Send + 'static
     {
-        let mut receive_stream = self.stream.try_clone().map_err(ScError::from)?;
+        let mut receive_stream = self.stream.try_clone_boxed().map_err(ScError::from)?;
+        let writer = Arc::clone(&self.writer);
+        let close_state = Arc::clone(&self.close_state);
+        let heartbeat = self
+            .config
+            .heartbeat_interval
+            .map(|interval| Heartbeat::new(interval, self.config.heartbeat_timeout));
+        let mut decoder = FrameDecoder::with_role(Role::Client);
+        decoder.set_reserved_bits(self.reserved_bits);
+        decoder.set_max_frame_size(self.config.max_frame_size);
+        let assembler = MessageAssembler::new()
+            .with_extensions(Arc::clone(&self.extensions))
+            .with_max_message_size(self.config.max_message_size);
+        decoder.extend(&std::mem::take(&mut self.buffered));
+        self.receiving = true;
         thread::spawn(move || {
-            let mut buffer = [0; 512];
-            loop {
-                match receive_stream.read(&mut buffer) {
-                    Ok(size) => {
-                        if size == 0 {
-                            break;
+            let peer_close = Self::receive_frames(
+                receive_stream.as_mut(),
+                &writer,
+                &close_state,
+                heartbeat,
+                decoder,
+                assembler,
+                receive_func,
+            );
+            Self::finish_close(&close_state, peer_close);
+        });
+        Ok(())
+    }
+
+    pub fn on_close<F>(&mut self, close_func: F)
+    where
+        F: Fn(Option<CloseFrame>) + Send + 'static
+    {
+        let (lock, _) = &*self.close_state;
+        lock.lock().unwrap().on_close = Some(Box::new(close_func));
+    }
+
+    pub fn close(&mut self) -> Result<Option<CloseFrame>> {
+        self.close_with(CloseFrame::new(CloseCode::Normal, ""))
+    }
+
+    // Runs the closing handshake and returns the close frame the server answered
+    // with, `None` if it closed without a status code or never answered in time.
+    pub fn close_with(&mut self, close_frame: CloseFrame) -> Result<Option<CloseFrame>> {
+        {
+            let (lock, _) = &*self.close_state;
+            let mut state = lock.lock().unwrap();
+            if state.status == ConnectionStatus::Closed {
+                return Ok(state.peer_close.clone());
+            }
+            state.status = ConnectionStatus::Closing;
+        }
+
+        if let Err(e) = Self::send_close(&self.writer, &close_frame) {
+            let _ = self.stream.shutdown(Shutdown::Both);
+            return Err(e);
+        }
+
+        let peer_close = if self.receiving {
+            let (lock, cvar) = &*self.close_state;
+            let state = lock.lock().unwrap();
+            let (state, _) = cvar
+                .wait_timeout_while(state, self.config.close_timeout, |state| {
+                    state.status != ConnectionStatus::Closed
+                })
+                .unwrap();
+            state.peer_close.clone()
+        } else {
+            let peer_close = self.wait_for_close()?;
+            Self::finish_close(&self.close_state, peer_close.clone());
+            peer_close
+        };
+
+        match self.stream.shutdown(Shutdown::Both) {
+            Err(e) if e.kind() != ErrorKind::NotConnected => Err(ScError::from(e)),
+            _ => Ok(peer_close),
+        }
+    }
+
+    fn receive_frames<F>(
+        stream: &mut dyn Transport,
+        writer: &SharedStream,
+        close_state: &SharedCloseState,
+        mut heartbeat: Option<Heartbeat>,
+        mut decoder: FrameDecoder,
+        mut assembler: MessageAssembler,
+        receive_func: F,
+    ) -> Option<CloseFrame>
+    where
+        F: Fn(Message)
+    {
+        let mut buffer = [0; 512];
+        // frames that came with the handshake response are already in the decoder
+        let mut pending = decoder.buffered() > 0;
+        loop {
+            if let Some(heartbeat) = heartbeat.as_mut() {
+                match heartbeat.poll(Instant::now()) {
+                    HeartbeatAction::SendPing => {
+                        let ping = DataFrame::from_data(b"", OpCode::Ping, true);
+                        if let Err(e) = Self::write_frames(writer, ping.into_iter().collect()) {
+                            eprintln!("Failed to send ping: {}", e);
+                            return None;
                         }
-                        let received_frame: DataFrame = DataFrame::try_from(&buffer[..size]).unwrap();
-                        let received_message: String = String::from_utf8(received_frame.payload).unwrap();
-                        receive_func(received_message);
                     },
+                    HeartbeatAction::PeerDead => {
+                        eprintln!("Server did not answer ping in time, dropping connection");
+                        let _ = stream.shutdown(Shutdown::Both);
+                        return None;
+                    },
+                    HeartbeatAction::Idle => {},
+                }
+                if let Err(e) = stream.set_read_timeout(Some(heartbeat.remaining(Instant::now()))) {
+                    eprintln!("Failed to set read timeout: {}", e);
+                    return None;
+                }
+            }
+
+            let data = if std::mem::take(&mut pending) {
+                &[][..]
+            } else {
+                match stream.read(&mut buffer) {
+                    Ok(0) => return None,
+                    Ok(size) => &buffer[..size],
+                    Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => continue,
                     Err(e) => {
                         eprintln!("Failed to receive frame: {}", e);
-                        break;
+                        return None;
                     }
                 }
+            };
+            let received_frames: Vec<DataFrame> = match decoder.feed(data) {
+                Ok(frames) => frames,
+                Err(e) => {
+                    eprintln!("Failed to decode frame: {}", e);
+                    let _ = Self::send_close(writer, &CloseFrame::new(e.close_code(), ""));
+                    return None;
+                }
+            };
+            for received_frame in received_frames {
+                let message = match assembler.push(received_frame) {
+                    Ok(Some(message)) => message,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        eprintln!("Failed to assemble message: {}", e);
+                        let _ = Self::send_close(writer, &CloseFrame::new(e.close_code(), ""));
+                        return None;
+                    }
+                };
+                match &message {
+                    Message::Close(peer_close) => {
+                        let (lock, _) = &**close_state;
+                        if lock.lock().unwrap().status != ConnectionStatus::Closing {
+                            // answer the server's close and shut down
+                            let code = peer_close.as_ref().map_or(CloseCode::Normal, |close| close.code);
+                            let _ = Self::send_close(writer, &CloseFrame::new(code, ""));
+                            let _ = stream.shutdown(Shutdown::Both);
+                        }
+                        let peer_close = peer_close.clone();
+                        receive_func(message);
+                        return peer_close;
+                    }
+                    Message::Ping(payload) => {
+                        let pong = DataFrame::from_data(payload, OpCode::Pong, true);
+                        if let Err(e) = Self::write_frames(writer, pong.into_iter().collect()) {
+                            eprintln!("Failed to send pong: {}", e);
+                        }
+                    }
+                    Message::Pong(_) => {
+                        if let Some(heartbeat) = heartbeat.as_mut() {
+                            heartbeat.pong_received();
+                        }
+                    }
+                    Message::Text(_) | Message::Binary(_) => {}
+                }
+                receive_func(message);
             }
-        });
-        Ok(())
+        }
     }
 
-    pub fn close(&mut self) -> Result<()>{
-        //TODO send close frame
-        self.stream.shutdown(Shutdown::Both).map_err(ScError::from)?;
+    fn finish_close(close_state: &SharedCloseState, peer_close: Option<CloseFrame>) {
+        let (lock, cvar) = &**close_state;
+        let mut state = lock.lock().unwrap();
+        let closed_by_server = state.status != ConnectionStatus::Closing;
+        state.status = ConnectionStatus::Closed;
+        state.peer_close = peer_close.clone();
+        let on_close = if closed_by_server { state.on_close.take() } else { None };
+        cvar.notify_all();
+        drop(state);
 
-        Ok(())
+        if let Some(on_close) = on_close {
+            on_close(peer_close);
+        }
     }
 
-    fn perform_handshake(mut stream: TcpStream, url: WebSocketUrl) -> Result<TcpStream>{
-        let resource_name = url.resource_name();
-        let host = match url.host.find(':') {
-            Some(idx) => {
-                url.host[..idx].to_string()
-            },
-            None => {
-                url.host
+    fn wait_for_close(&mut self) -> Result<Option<CloseFrame>> {
+        let deadline = Instant::now() + self.config.close_timeout;
+        let mut buffer = [0; 512];
+        let mut decoder = FrameDecoder::with_role(Role::Client);
+        decoder.set_reserved_bits(self.reserved_bits);
+        decoder.set_max_frame_size(self.config.max_frame_size);
+        let buffered = std::mem::take(&mut self.buffered);
+        let mut buffered = Some(&buffered[..]).filter(|buffered| !buffered.is_empty());
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
             }
-        };
+            self.stream.set_read_timeout(Some(remaining)).map_err(ScError::from)?;
 
-        let websocket_key = Self::generate_key();
+            let read = match buffered.take() {
+                Some(data) => Ok(data),
+                None => self.stream.read(&mut buffer).map(|size| &buffer[..size]),
+            };
+            match read {
+                Ok([]) => return Ok(None),
+                Ok(data) => {
+                    let received_frames = decoder.feed(data).map_err(|_| ScError::DataFrameError)?;
+                    for received_frame in received_frames {
+                        if received_frame.get_opcode() == OpCode::ConnectionClose {
+                            return Ok(CloseFrame::from_payload(&received_frame.payload).ok().flatten());
+                        }
+                    }
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                    return Ok(None);
+                },
+                Err(e) => return Err(ScError::from(e)),
+            }
+        }
+    }
 
-        let websocket_request = format!(
-            "GET {} HTTP/1.1\r\n\
-            Host: {}\r\n\
-            Upgrade: websocket\r\n\
-            Connection: Upgrade\r\n\
-            Sec-WebSocket-Key: {}\r\n\
-            Sec-WebSocket-Version: 13\r\n\r\n",
-            resource_name, host, websocket_key
-        );
+    fn send_close(writer: &SharedStream, close_frame: &CloseFrame) -> Result<()> {
+        match close_frame.to_dataframe(true) {
+            Some(frame) => Self::write_frames(writer, vec![frame]),
+            None => Err(ScError::DataFrameError),
+        }
+    }
 
-        stream.write_all(websocket_request.as_bytes()).map_err(ScError::from)?;
+    fn write_frames(writer: &SharedStream, frames: Vec<DataFrame>) -> Result<()> {
+        let mut stream = writer.lock().unwrap();
+        for frame in frames {
+            frame.write_to(&mut *stream).map_err(ScError::from)?;
+        }
         stream.flush().map_err(ScError::from)?;
 
-        Self::verify_handshake_response(&websocket_key, &mut stream)?;
-
-        Ok(stream)
+        Ok(())
     }
 
-    fn generate_key() -> String {
-        let mut nonce: [u8; 16] = [0; 16];
-        rand::thread_rng().fill_bytes(&mut nonce);
+    // `wss://` runs the websocket over TLS, verified against the configured roots.
+    fn wrap_transport(stream: TcpStream, url: &WebSocketUrl, config: &ClientConfig) -> Result<Box<dyn Transport>> {
+        if !url.is_secure() {
+            return Ok(Box::new(stream));
+        }
 
-        encode(&nonce)
+        #[cfg(feature = "tls")]
+        {
+            let tls_stream = TlsStream::connect(config.tls_config()?, url.host_name(), stream)?;
+            Ok(Box::new(tls_stream))
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            let _ = config;
+            Err(ScError::TlsNotEnabled)
+        }
     }
 
-    fn verify_handshake_response(key: &str, stream: &mut TcpStream) -> Result<()> {
-        let mut buffer = [0u8; 512];
-        loop {
-            match stream.read(&mut buffer) {
-                Ok(n) if n == 0 => {
-                    return Err(ScError::ServerClosed);
-                },
-                Ok(n) => {
-                    let received_data = String::from_utf8_lossy(&buffer[..n]);
-                    let mut lines = received_data.lines();
+    fn perform_handshake(
+        mut stream: Box<dyn Transport>,
+        url: WebSocketUrl,
+        config: &ClientConfig,
+    ) -> Result<Handshake> {
+        let websocket_key = utils::generate_key();
+        let websocket_request = utils::handshake_request(&url, &websocket_key, config);
 
-                    if let Some(status_line) = lines.next() {
-                        let _ = utils::verify_status_line(&status_line)?;
-                    }
+        stream.write_all(websocket_request.as_bytes()).map_err(ScError::from)?;
+        stream.flush().map_err(ScError::from)?;
 
-                    let mut resp_headers: HashMap<String, String> = HashMap::new();
-                    for line in lines {
-                        if line.is_empty(){
-                            break;
-                        }
+        let (resp_headers, buffered) = Self::verify_handshake_response(&websocket_key, stream.as_mut())?;
+        let extensions = utils::negotiate_extensions(config, &resp_headers)?;
+        let protocol = utils::negotiate_protocol(config, &resp_headers)?;
 
-                        if let Some((key, value)) = line.split_once(": "){
-                            let l_key = key.to_ascii_lowercase();
-                            resp_headers.insert(l_key, value.to_string());
-                        }
-                    }
+        Ok((stream, extensions, protocol, buffered))
+    }
 
-                    let _ = utils::validate_headers(&resp_headers, &key)?;
+    // Reads until the end of the response head, whatever the server sent after
+    // it is returned for the frame decoder.
+    fn verify_handshake_response(key: &str, stream: &mut dyn Transport) -> Result<(HashMap<String, String>, Vec<u8>)> {
+        let mut response = Vec::new();
+        let mut buffer = [0u8; 512];
+        let head_end = loop {
+            if let Some(head_end) = utils::head_end(&response) {
+                break head_end;
+            }
+            if response.len() > utils::MAX_HANDSHAKE_SIZE {
+                return Err(ScError::InvalidHttpResponse);
+            }
 
-                    return Ok(())
-                },
-                Err(e) => {
-                    return Err(ScError::from(e));
-                }
+            match stream.read(&mut buffer) {
+                Ok(0) => return Err(ScError::ServerClosed),
+                Ok(size) => response.extend_from_slice(&buffer[..size]),
+                Err(e) => return Err(ScError::from(e)),
             }
-        }
+        };
+
+        let resp_headers = utils::verify_handshake_response(&String::from_utf8_lossy(&response[..head_end]), key)?;
+        Ok((resp_headers, response.split_off(head_end)))
     }
-}
\ No newline at end of file
+}
@@ -16,22 +16,7 @@ impl ToSocketAddrs for WebSocketUrl {
     type Iter = std::vec::IntoIter<SocketAddr>;
 
     fn to_socket_addrs(&self) -> io::Result<Self::Iter> {
-        let port = match self.port() {
-            Ok(port) => port,
-            Err(_e) => {
-                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid port or scheme"));
-            }
-        };
-
-        let host = if let Some(idx) = self.host.find(':') {
-            &self.host[..idx]
-        } else {
-            &self.host
-        };
-
-        let addr_string = format!("{}:{}", host, port);
-        
-        addr_string.to_socket_addrs()
+        self.address()?.to_socket_addrs()
     }
 }
 
@@ -58,6 +43,30 @@ impl WebSocketUrl {
         }
     }
 
+    // `host:port` with the scheme's default port filled in, ready to be resolved.
+    pub fn address(&self) -> io::Result<String> {
+        let port = match self.port() {
+            Ok(port) => port,
+            Err(_e) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid port or scheme"));
+            }
+        };
+
+        Ok(format!("{}:{}", self.host_name(), port))
+    }
+
+    // Host without the port, this is what TLS certificates are checked against.
+    pub fn host_name(&self) -> &str {
+        match self.host.find(':') {
+            Some(idx) => &self.host[..idx],
+            None => &self.host,
+        }
+    }
+
+    pub fn is_secure(&self) -> bool {
+        self.scheme == "wss"
+    }
+
     pub fn resource_name(&self) -> String {
         let mut resource_name = String::new();
         
@@ -67,6 +76,10 @@ impl WebSocketUrl {
             }
             resource_name.push_str(path);
         }
+        // the request target can't be empty, a bare host asks for `/`
+        if resource_name.is_empty() {
+            resource_name.push('/');
+        }
         
         if let Some(query) = &self.query {
             if resource_name.ends_with('/'){
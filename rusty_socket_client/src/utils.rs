@@ -1,12 +1,25 @@
+use crate::ClientConfig;
 use crate::ScError;
 use crate::Result;
+use crate::WebSocketUrl;
 use std::collections::HashMap;
 
+use rusty_socket_core::Extensions;
+
 use cryptography::SHA1;
 use base64;
+use rand::RngCore;
 
 const WS_GUID: &'static str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
 
+// longest handshake response we wait for before giving up on the server
+pub(crate) const MAX_HANDSHAKE_SIZE: usize = 8192;
+
+// Where the response head ends, `None` until its blank line has arrived.
+pub(crate) fn head_end(response: &[u8]) -> Option<usize> {
+    response.windows(4).position(|window| window == b"\r\n\r\n").map(|idx| idx + 4)
+}
+
 
 pub fn verify_status_line(status_line: &str) -> Result<()> {
     let resp_line: Vec<&str> = status_line.splitn(3, ' ').collect();
@@ -90,4 +103,83 @@ pub fn validate_headers(resp_headers: &HashMap<String, String>, client_key: &str
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn generate_key() -> String {
+    let mut nonce: [u8; 16] = [0; 16];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    base64::encode(&nonce)
+}
+
+pub fn handshake_request(url: &WebSocketUrl, websocket_key: &str, config: &ClientConfig) -> String {
+    let resource_name = url.resource_name();
+    let host = url.host_name();
+    let extensions = match Extensions::offer_header(&config.extensions()) {
+        Some(offers) => format!("Sec-WebSocket-Extensions: {}\r\n", offers),
+        None => String::new(),
+    };
+    let protocols = if config.protocols.is_empty() {
+        String::new()
+    } else {
+        format!("Sec-WebSocket-Protocol: {}\r\n", config.protocols.join(", "))
+    };
+
+    format!(
+        "GET {} HTTP/1.1\r\n\
+        Host: {}\r\n\
+        Upgrade: websocket\r\n\
+        Connection: Upgrade\r\n\
+        Sec-WebSocket-Key: {}\r\n\
+        Sec-WebSocket-Version: 13\r\n\
+        {}{}\r\n",
+        resource_name, host, websocket_key, extensions, protocols
+    )
+}
+
+// Checks the server's handshake answer and returns its headers, keyed in lower case.
+pub fn verify_handshake_response(response: &str, client_key: &str) -> Result<HashMap<String, String>> {
+    let mut lines = response.lines();
+
+    if let Some(status_line) = lines.next() {
+        verify_status_line(status_line)?;
+    }
+
+    let mut resp_headers: HashMap<String, String> = HashMap::new();
+    for line in lines {
+        if line.is_empty(){
+            break;
+        }
+
+        if let Some((key, value)) = line.split_once(": "){
+            let l_key = key.to_ascii_lowercase();
+            resp_headers.insert(l_key, value.to_string());
+        }
+    }
+
+    validate_headers(&resp_headers, client_key)?;
+
+    Ok(resp_headers)
+}
+
+// The extensions the server accepted, it may only pick ones we offered.
+pub fn negotiate_extensions(config: &ClientConfig, resp_headers: &HashMap<String, String>) -> Result<Extensions> {
+    match resp_headers.get("sec-websocket-extensions") {
+        Some(response) => {
+            Extensions::accept(config.extensions(), response).map_err(|_| ScError::InvalidHandshakeHeader)
+        }
+        None => Ok(Extensions::new()),
+    }
+}
+
+// The subprotocol the server picked, it has to be one we offered and may only
+// be left out if we offered none.
+pub fn negotiate_protocol(config: &ClientConfig, resp_headers: &HashMap<String, String>) -> Result<Option<String>> {
+    match resp_headers.get("sec-websocket-protocol") {
+        Some(protocol) if config.protocols.iter().any(|offered| offered == protocol.trim()) => {
+            Ok(Some(protocol.trim().to_string()))
+        }
+        None if config.protocols.is_empty() => Ok(None),
+        _ => Err(ScError::ProtocolNotOffered),
+    }
+}
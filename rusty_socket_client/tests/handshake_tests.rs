@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use cryptography::SHA1;
+use rusty_socket_client::utils::{handshake_request, negotiate_protocol};
+use rusty_socket_client::{ClientConfig, Message, ScError, SocketClient, WebSocketUrl};
+
+fn response_headers(protocol: Option<&str>) -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+    if let Some(protocol) = protocol {
+        headers.insert(String::from("sec-websocket-protocol"), protocol.to_string());
+    }
+    headers
+}
+
+#[test]
+fn test_request_offers_protocols_in_order() {
+    let url = WebSocketUrl::from_url("ws://localhost:8080/graphql").unwrap();
+    let config = ClientConfig::default().with_protocols(["graphql-transport-ws", "v2.json"]);
+
+    let request = handshake_request(&url, "key", &config);
+    assert!(request.contains("Sec-WebSocket-Protocol: graphql-transport-ws, v2.json\r\n"));
+    assert!(request.ends_with("\r\n\r\n"));
+
+    let request = handshake_request(&url, "key", &ClientConfig::default());
+    assert!(!request.contains("Sec-WebSocket-Protocol"));
+}
+
+#[test]
+fn test_server_must_pick_an_offered_protocol() {
+    let config = ClientConfig::default().with_protocols(["graphql-transport-ws", "v2.json"]);
+
+    assert_eq!(
+        negotiate_protocol(&config, &response_headers(Some("v2.json"))),
+        Ok(Some(String::from("v2.json")))
+    );
+    assert_eq!(
+        negotiate_protocol(&config, &response_headers(Some("chat"))),
+        Err(ScError::ProtocolNotOffered)
+    );
+    assert_eq!(negotiate_protocol(&config, &response_headers(None)), Err(ScError::ProtocolNotOffered));
+
+    let config = ClientConfig::default();
+    assert_eq!(negotiate_protocol(&config, &response_headers(None)), Ok(None));
+    assert_eq!(
+        negotiate_protocol(&config, &response_headers(Some("v2.json"))),
+        Err(ScError::ProtocolNotOffered)
+    );
+}
+
+#[test]
+fn test_frame_sent_with_the_handshake_response_is_kept() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}/", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buffer = [0; 512];
+        while !request.ends_with(b"\r\n\r\n") {
+            let size = stream.read(&mut buffer).unwrap();
+            request.extend_from_slice(&buffer[..size]);
+        }
+        let request = String::from_utf8(request).unwrap();
+        let key = request
+            .lines()
+            .find_map(|line| line.strip_prefix("Sec-WebSocket-Key: "))
+            .unwrap();
+        let accept = base64::encode(&SHA1::new().hash(&format!("{}258EAFA5-E914-47DA-95CA-C5AB0DC85B11", key)));
+
+        // a head longer than one read, followed by a frame in the same write
+        let mut response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\nX-Padding: {}\r\n\r\n",
+            accept,
+            "a".repeat(600)
+        )
+        .into_bytes();
+        response.extend_from_slice(&[0x81, 5]);
+        response.extend_from_slice(b"hello");
+        stream.write_all(&response).unwrap();
+
+        // hold the connection open until the client goes away
+        let _ = stream.read(&mut buffer);
+    });
+
+    let mut client = SocketClient::build(&url).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    client
+        .on_receive(move |message| {
+            let _ = sender.send(message);
+        })
+        .unwrap();
+
+    let received = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(received, Message::Text(String::from("hello")));
+}
@@ -46,6 +46,7 @@ fn test_valid_url_with_host_only() {
     assert_eq!(wsu.path, None);
     assert_eq!(wsu.query, None);
     assert_eq!(wsu.fragment, None);
+    assert_eq!(wsu.resource_name(), "/");
 }
 
 #[test]
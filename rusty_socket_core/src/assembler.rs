@@ -0,0 +1,114 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{DataFrame, Extensions, Message, OpCode, RsError, RsResult, Utf8Validator};
+
+pub struct MessageAssembler {
+    opcode: Option<OpCode>,
+    payload: Vec<u8>,
+    // checks text messages as their fragments arrive
+    utf8: Utf8Validator,
+    extensions: Option<Arc<Mutex<Extensions>>>,
+    // total payload a data message may reach once its fragments are joined and
+    // extensions decoded it, larger ones fail with 1009
+    max_message_size: usize,
+}
+
+impl Default for MessageAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageAssembler {
+    pub fn new() -> Self {
+        MessageAssembler {
+            opcode: None,
+            payload: Vec::new(),
+            utf8: Utf8Validator::new(),
+            extensions: None,
+            max_message_size: usize::MAX,
+        }
+    }
+
+    // Data frames are decoded by these extensions before they are assembled,
+    // the same set encodes outgoing messages.
+    pub fn with_extensions(mut self, extensions: Arc<Mutex<Extensions>>) -> Self {
+        self.extensions = Some(extensions);
+        self
+    }
+
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    // Returns the complete message once its final fragment arrives. Control frames
+    // may show up between fragments and are handed back straight away without
+    // disturbing the message being assembled.
+    pub fn push(&mut self, frame: DataFrame) -> RsResult<Option<Message>> {
+        if frame.is_control_frame() {
+            // extensions never see control frames, so they cannot use RSV bits
+            if frame.get_rsv_bits() != 0 {
+                return Err(RsError::ReservedBitsSet);
+            }
+            return Message::from_payload(frame.get_opcode(), frame.payload).map(Some);
+        }
+
+        let frame = match &self.extensions {
+            Some(extensions) => {
+                // what is left of the message budget once earlier fragments are counted
+                let remaining = self.max_message_size.saturating_sub(self.payload.len());
+                extensions.lock().unwrap().decode(frame, remaining)?
+            }
+            None if frame.get_rsv_bits() != 0 => return Err(RsError::ReservedBitsSet),
+            None => frame,
+        };
+        let opcode = frame.get_opcode();
+        let is_final = frame.is_final_fragment();
+
+        match opcode {
+            OpCode::ContinuationFrame => {
+                if self.opcode.is_none() {
+                    return Err(RsError::UnexpectedContinuation);
+                }
+                if self.payload.len() + frame.payload.len() > self.max_message_size {
+                    return Err(RsError::MessageTooLarge);
+                }
+                if self.opcode == Some(OpCode::Text) {
+                    self.utf8.feed(&frame.payload)?;
+                }
+                self.payload.extend_from_slice(&frame.payload);
+            }
+            OpCode::Text | OpCode::Binary => {
+                if self.opcode.is_some() {
+                    return Err(RsError::UnfinishedMessage);
+                }
+                if frame.payload.len() > self.max_message_size {
+                    return Err(RsError::MessageTooLarge);
+                }
+                if opcode == OpCode::Text {
+                    self.utf8.reset();
+                    self.utf8.feed(&frame.payload)?;
+                }
+                self.opcode = Some(opcode);
+                self.payload = frame.payload;
+            }
+            _ => return Err(RsError::InvalidOpCode),
+        }
+
+        if is_final {
+            if self.opcode == Some(OpCode::Text) {
+                self.utf8.finish()?;
+            }
+            let message_opcode = self.opcode.take().unwrap_or(OpCode::Unknown);
+            let message_payload = std::mem::take(&mut self.payload);
+            return Message::from_payload(message_opcode, message_payload).map(Some);
+        }
+
+        Ok(None)
+    }
+
+    pub fn is_assembling(&self) -> bool {
+        self.opcode.is_some()
+    }
+}
@@ -0,0 +1,411 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadHalf, WriteHalf};
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::{
+    CloseCode, CloseFrame, ConnectionStatus, DataFrame, Extensions, FrameDecoder,
+    FrameHeader, Heartbeat, HeartbeatAction, Identity, Message, MessageAssembler, OpCode, Role, RsError, RsResult,
+};
+use crate::frame_header::{mask_chunk, MASK_CHUNK_SIZE};
+
+struct SocketState {
+    status: ConnectionStatus,
+    close_deadline: Option<Instant>,
+}
+
+pub struct AsyncSender<S> {
+    writer: Arc<AsyncMutex<WriteHalf<S>>>,
+    state: Arc<Mutex<SocketState>>,
+    role: Role,
+    fragment_size: usize,
+    close_timeout: Duration,
+    extensions: Arc<Mutex<Extensions>>,
+}
+
+impl<S> Clone for AsyncSender<S> {
+    fn clone(&self) -> Self {
+        AsyncSender {
+            writer: Arc::clone(&self.writer),
+            state: Arc::clone(&self.state),
+            role: self.role,
+            fragment_size: self.fragment_size,
+            close_timeout: self.close_timeout,
+            extensions: Arc::clone(&self.extensions),
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite> AsyncSender<S> {
+    pub fn status(&self) -> ConnectionStatus {
+        self.state.lock().unwrap().status
+    }
+
+    pub async fn send<M: Into<Message>>(&self, message: M) -> RsResult<()> {
+        let message: Message = message.into();
+        if let Message::Close(close_frame) = message {
+            return self.close(close_frame.unwrap_or_else(|| CloseFrame::new(CloseCode::Normal, ""))).await;
+        }
+        if self.status() != ConnectionStatus::Open {
+            return Err(RsError::ConnectionClosed);
+        }
+
+        // encode while holding the writer so the wire order matches the order
+        // messages went through the extensions
+        let mut writer = self.writer.lock().await;
+        let mask = self.role.masks_frames();
+        let frames = {
+            let mut extensions = self.extensions.lock().unwrap();
+            if extensions.is_empty() || message.is_control() {
+                None
+            } else {
+                Some(extensions.to_frames(&message, mask, self.fragment_size)?)
+            }
+        };
+
+        // frames are written straight from the payload they carry
+        match frames {
+            None => {
+                for (header, payload) in message.frame_headers(mask, self.fragment_size)? {
+                    write_frame(&mut *writer, &header, payload).await?;
+                }
+            }
+            Some(frames) => {
+                for frame in frames {
+                    // the payload of a `DataFrame` is already masked
+                    let (header, header_size) = frame.header().encode();
+                    writer.write_all(&header[..header_size]).await?;
+                    writer.write_all(&frame.payload).await?;
+                }
+            }
+        }
+        writer.flush().await?;
+
+        Ok(())
+    }
+
+    // Sends our close frame, the receiving half completes the handshake when the
+    // peer answers or gives up once the close timeout has passed.
+    pub async fn close(&self, close_frame: CloseFrame) -> RsResult<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.status != ConnectionStatus::Open {
+                return Ok(());
+            }
+            state.status = ConnectionStatus::Closing;
+            state.close_deadline = Some(Instant::now() + self.close_timeout);
+        }
+
+        let frame = close_frame
+            .to_dataframe(self.role.masks_frames())
+            .ok_or(RsError::InvalidOpCode)?;
+        self.write_frames(vec![frame]).await
+    }
+
+    async fn write_frames(&self, frames: Vec<DataFrame>) -> RsResult<()> {
+        let frame_bytes: Vec<u8> = frames.into_iter().flat_map(Vec::from).collect();
+        let mut writer = self.writer.lock().await;
+        writer.write_all(&frame_bytes).await?;
+        writer.flush().await?;
+
+        Ok(())
+    }
+
+    async fn shutdown(&self) {
+        self.state.lock().unwrap().status = ConnectionStatus::Closed;
+        let _ = self.writer.lock().await.shutdown().await;
+    }
+}
+
+// Writes the header and then the payload from the caller's buffer, a masked
+// payload goes out a few kilobytes at a time through a fixed buffer.
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, header: &FrameHeader, payload: &[u8]) -> io::Result<()> {
+    let (header_bytes, header_size) = header.encode();
+    writer.write_all(&header_bytes[..header_size]).await?;
+
+    match header.masking_key {
+        Some(masking_key) => {
+            let mut buffer = [0u8; MASK_CHUNK_SIZE];
+            for chunk in payload.chunks(MASK_CHUNK_SIZE) {
+                writer.write_all(mask_chunk(chunk, masking_key, &mut buffer)).await?;
+            }
+            Ok(())
+        }
+        None => writer.write_all(payload).await,
+    }
+}
+
+pub struct AsyncReceiver<S> {
+    reader: ReadHalf<S>,
+    sender: AsyncSender<S>,
+    decoder: FrameDecoder,
+    assembler: MessageAssembler,
+    heartbeat: Option<Heartbeat>,
+}
+
+impl<S: AsyncRead + AsyncWrite> AsyncReceiver<S> {
+    // Yields the next message from the peer, `None` once the connection is closed.
+    // Pings are answered and close frames echoed before they are handed out.
+    pub async fn next(&mut self) -> Option<RsResult<Message>> {
+        let mut buffer = [0u8; 512];
+        loop {
+            match self.decoder.next_frame() {
+                Ok(Some(frame)) => {
+                    let message = match self.assembler.push(frame) {
+                        Ok(Some(message)) => message,
+                        Ok(None) => continue,
+                        Err(e) => return Some(Err(self.fail(e).await)),
+                    };
+                    match self.handle_message(message).await {
+                        Ok(Some(message)) => return Some(Ok(message)),
+                        Ok(None) => continue,
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => return Some(Err(self.fail(e).await)),
+            }
+
+            if self.sender.status() == ConnectionStatus::Closed {
+                return None;
+            }
+
+            let read = match self.read_timeout() {
+                Some(timeout) => match tokio::time::timeout(timeout, self.reader.read(&mut buffer)).await {
+                    Ok(read) => read,
+                    Err(_) => {
+                        if let Err(e) = self.handle_timeout().await {
+                            return Some(Err(e));
+                        }
+                        continue;
+                    }
+                },
+                None => self.reader.read(&mut buffer).await,
+            };
+
+            match read {
+                Ok(0) => {
+                    self.sender.shutdown().await;
+                    return None;
+                }
+                Ok(size) => self.decoder.extend(&buffer[..size]),
+                Err(e) => {
+                    self.sender.shutdown().await;
+                    return Some(Err(RsError::from(e)));
+                }
+            }
+        }
+    }
+
+    async fn handle_message(&mut self, message: Message) -> RsResult<Option<Message>> {
+        let status = self.sender.status();
+        match &message {
+            Message::Close(peer_close) => {
+                if status == ConnectionStatus::Open {
+                    // answered here, the caller still gets the close
+                    let code = peer_close.as_ref().map_or(CloseCode::Normal, |close| close.code);
+                    let _ = self.sender.close(CloseFrame::new(code, "")).await;
+                }
+                self.sender.shutdown().await;
+                return Ok(Some(message));
+            }
+            Message::Ping(payload) if status == ConnectionStatus::Open => {
+                let pong = DataFrame::from_data(payload, OpCode::Pong, self.sender.role.masks_frames());
+                self.sender.write_frames(pong.into_iter().collect()).await?;
+            }
+            Message::Pong(_) => {
+                if let Some(heartbeat) = self.heartbeat.as_mut() {
+                    heartbeat.pong_received();
+                }
+            }
+            _ => {}
+        }
+
+        // only handed out while open, see `ConnectionStatus`
+        if status == ConnectionStatus::Open {
+            Ok(Some(message))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn read_timeout(&self) -> Option<Duration> {
+        let close_deadline = self.sender.state.lock().unwrap().close_deadline;
+        match (close_deadline, self.heartbeat.as_ref()) {
+            (Some(deadline), _) => Some(deadline.saturating_duration_since(Instant::now())),
+            (None, Some(heartbeat)) => Some(heartbeat.remaining(Instant::now())),
+            (None, None) => None,
+        }
+    }
+
+    async fn handle_timeout(&mut self) -> RsResult<()> {
+        let close_deadline = self.sender.state.lock().unwrap().close_deadline;
+        if let Some(deadline) = close_deadline {
+            if Instant::now() >= deadline {
+                self.sender.shutdown().await;
+            }
+            return Ok(());
+        }
+
+        let action = match self.heartbeat.as_mut() {
+            Some(heartbeat) => heartbeat.poll(Instant::now()),
+            None => return Ok(()),
+        };
+        match action {
+            HeartbeatAction::SendPing => {
+                let ping = DataFrame::from_data(b"", OpCode::Ping, self.sender.role.masks_frames());
+                self.sender.write_frames(ping.into_iter().collect()).await
+            }
+            HeartbeatAction::PeerDead => {
+                self.sender.shutdown().await;
+                Err(RsError::from(io::Error::new(io::ErrorKind::TimedOut, "Peer did not answer ping")))
+            }
+            HeartbeatAction::Idle => Ok(()),
+        }
+    }
+
+    // Sends the error's close code and shuts down straight away.
+    async fn fail(&mut self, error: RsError) -> RsError {
+        let _ = self.sender.close(CloseFrame::new(error.close_code(), &error.to_string())).await;
+        self.sender.shutdown().await;
+        error
+    }
+}
+
+// A websocket over any tokio stream. Messages go through the inherent `next` and
+// `send` methods, it does not implement the `futures` `Stream` and `Sink` traits.
+pub struct AsyncSocket<S> {
+    sender: AsyncSender<S>,
+    receiver: AsyncReceiver<S>,
+    protocol: Option<String>,
+    identity: Identity,
+}
+
+impl<S: AsyncRead + AsyncWrite> AsyncSocket<S> {
+    pub fn new(stream: S, role: Role) -> Self {
+        let (reader, writer) = io::split(stream);
+        let state = SocketState {
+            status: ConnectionStatus::Open,
+            close_deadline: None,
+        };
+        let sender = AsyncSender {
+            writer: Arc::new(AsyncMutex::new(writer)),
+            state: Arc::new(Mutex::new(state)),
+            role,
+            fragment_size: 0,
+            close_timeout: Duration::from_secs(5),
+            extensions: Arc::new(Mutex::new(Extensions::new())),
+        };
+
+        AsyncSocket {
+            receiver: AsyncReceiver {
+                reader,
+                sender: sender.clone(),
+                decoder: FrameDecoder::with_role(role),
+                assembler: MessageAssembler::new(),
+                heartbeat: None,
+            },
+            sender,
+            protocol: None,
+            identity: Identity::anonymous(),
+        }
+    }
+
+    // Bytes that were read together with the handshake and already belong to frames.
+    pub fn buffered(mut self, data: &[u8]) -> Self {
+        self.receiver.decoder.extend(data);
+        self
+    }
+
+    pub fn fragment_size(mut self, fragment_size: Option<usize>) -> Self {
+        self.sender.fragment_size = fragment_size.unwrap_or(0);
+        self.receiver.sender.fragment_size = self.sender.fragment_size;
+        self
+    }
+
+    pub fn close_timeout(mut self, close_timeout: Duration) -> Self {
+        self.sender.close_timeout = close_timeout;
+        self.receiver.sender.close_timeout = close_timeout;
+        self
+    }
+
+    // Payload limits for incoming frames and messages, larger ones fail the
+    // connection with 1009.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.receiver.decoder.set_max_frame_size(max_frame_size);
+        self
+    }
+
+    pub fn max_message_size(mut self, max_message_size: usize) -> Self {
+        self.receiver.assembler = std::mem::take(&mut self.receiver.assembler).with_max_message_size(max_message_size);
+        self
+    }
+
+    pub fn heartbeat(mut self, interval: Option<Duration>, timeout: Duration) -> Self {
+        self.receiver.heartbeat = interval.map(|interval| Heartbeat::new(interval, timeout));
+        self
+    }
+
+    // Runs messages through the extensions agreed in the handshake.
+    pub fn extensions(mut self, extensions: Extensions) -> Self {
+        self.receiver.decoder.set_reserved_bits(extensions.rsv_bits());
+        let extensions = Arc::new(Mutex::new(extensions));
+        self.sender.extensions = Arc::clone(&extensions);
+        self.receiver.sender.extensions = Arc::clone(&extensions);
+        self.receiver.assembler = std::mem::take(&mut self.receiver.assembler).with_extensions(extensions);
+        self
+    }
+
+    // Records the subprotocol agreed in the handshake.
+    pub fn with_protocol(mut self, protocol: Option<String>) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    // Records who the handshake authenticated, see `ServerConfig::authenticator`.
+    pub fn with_identity(mut self, identity: Identity) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    pub fn status(&self) -> ConnectionStatus {
+        self.sender.status()
+    }
+
+    pub fn sender(&self) -> AsyncSender<S> {
+        self.sender.clone()
+    }
+
+    pub async fn next(&mut self) -> Option<RsResult<Message>> {
+        self.receiver.next().await
+    }
+
+    pub async fn send<M: Into<Message>>(&self, message: M) -> RsResult<()> {
+        self.sender.send(message).await
+    }
+
+    // Runs the whole closing handshake and returns the close frame the peer answered with.
+    pub async fn close(&mut self, close_frame: CloseFrame) -> RsResult<Option<CloseFrame>> {
+        self.sender.close(close_frame).await?;
+        while let Some(message) = self.receiver.next().await {
+            if let Ok(Message::Close(peer_close)) = message {
+                return Ok(peer_close);
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn split(self) -> (AsyncSender<S>, AsyncReceiver<S>) {
+        (self.sender, self.receiver)
+    }
+}
@@ -0,0 +1,132 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::dataframe::MAX_CONTROL_PAYLOAD;
+use crate::{DataFrame, OpCode, RsError, RsResult};
+
+// close frame payloads share the control frame limit with the 2 byte code
+const MAX_REASON_LENGTH: usize = MAX_CONTROL_PAYLOAD - 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CloseCode {
+    Normal,
+    GoingAway,
+    ProtocolError,
+    UnsupportedData,
+    InvalidPayload,
+    PolicyViolation,
+    MessageTooBig,
+    InternalError,
+    Application(u16),
+    Other(u16),
+}
+
+impl CloseCode {
+    pub fn is_application(&self) -> bool {
+        matches!(self, CloseCode::Application(_))
+    }
+}
+
+impl TryFrom<u16> for CloseCode {
+    type Error = RsError;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            1000 => Ok(CloseCode::Normal),
+            1001 => Ok(CloseCode::GoingAway),
+            1002 => Ok(CloseCode::ProtocolError),
+            1003 => Ok(CloseCode::UnsupportedData),
+            1007 => Ok(CloseCode::InvalidPayload),
+            1008 => Ok(CloseCode::PolicyViolation),
+            1009 => Ok(CloseCode::MessageTooBig),
+            1011 => Ok(CloseCode::InternalError),
+            4000..=4999 => Ok(CloseCode::Application(value)),
+            // 1010 and 1012-1014 are registered with IANA, 3000-3999 are for libraries and frameworks
+            1010 | 1012..=1014 | 3000..=3999 => Ok(CloseCode::Other(value)),
+            _ => Err(RsError::InvalidCloseCode),
+        }
+    }
+}
+
+impl From<CloseCode> for u16 {
+    fn from(value: CloseCode) -> Self {
+        match value {
+            CloseCode::Normal => 1000,
+            CloseCode::GoingAway => 1001,
+            CloseCode::ProtocolError => 1002,
+            CloseCode::UnsupportedData => 1003,
+            CloseCode::InvalidPayload => 1007,
+            CloseCode::PolicyViolation => 1008,
+            CloseCode::MessageTooBig => 1009,
+            CloseCode::InternalError => 1011,
+            CloseCode::Application(code) => code,
+            CloseCode::Other(code) => code,
+        }
+    }
+}
+
+impl fmt::Display for CloseCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", u16::from(*self))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloseFrame {
+    pub code: CloseCode,
+    pub reason: String,
+}
+
+impl fmt::Display for CloseFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.reason.is_empty() {
+            write!(f, "{}", self.code)
+        } else {
+            write!(f, "{} ({})", self.code, self.reason)
+        }
+    }
+}
+
+impl CloseFrame {
+    pub fn new(code: CloseCode, reason: &str) -> Self {
+        let mut end = reason.len().min(MAX_REASON_LENGTH);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        CloseFrame {
+            code,
+            reason: reason[..end].to_string(),
+        }
+    }
+
+    // An empty close payload is allowed and means no status code was given.
+    pub fn from_payload(payload: &[u8]) -> RsResult<Option<Self>> {
+        match payload.len() {
+            0 => Ok(None),
+            1 => Err(RsError::InvalidCloseCode),
+            len if len > MAX_REASON_LENGTH + 2 => Err(RsError::ProtocolError),
+            _ => {
+                let code = CloseCode::try_from(u16::from_be_bytes([payload[0], payload[1]]))?;
+                let reason = std::str::from_utf8(&payload[2..]).map_err(|_| RsError::InvalidUtf8)?;
+
+                Ok(Some(CloseFrame {
+                    code,
+                    reason: reason.to_string(),
+                }))
+            }
+        }
+    }
+
+    pub fn to_payload(&self) -> Vec<u8> {
+        let mut payload: Vec<u8> = Vec::with_capacity(2 + self.reason.len());
+        payload.extend_from_slice(&u16::from(self.code).to_be_bytes());
+        payload.extend_from_slice(self.reason.as_bytes());
+
+        payload
+    }
+
+    pub fn to_dataframe(&self, mask: bool) -> Option<DataFrame> {
+        DataFrame::from_data(self.to_payload(), OpCode::ConnectionClose, mask)
+    }
+}
@@ -1,6 +1,12 @@
+// Where a connection is in its lifetime. When the peer starts the close we echo
+// its code back and go straight on to `Closed`. Messages that arrive after our
+// own close frame went out are dropped. A protocol violation sends a close frame
+// with the reason but does not wait for the peer to answer it.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ConnectionStatus {
     Connecting,
     Open,
+    // our close frame is sent, waiting for the peer's
     Closing,
     Closed,
-}
\ No newline at end of file
+}
@@ -1,8 +1,13 @@
 use rand::RngCore;
 use std::convert::TryFrom;
 use std::fmt;
+use std::io::{self, IoSlice, Write};
 
-use crate::{ExtendedPayLoadLength, OpCode, RsError};
+use crate::frame_header::write_all_vectored;
+use crate::{ExtendedPayLoadLength, FrameHeader, OpCode, RsError, RsResult};
+
+// longest payload a ping, pong or close frame may carry
+pub(crate) const MAX_CONTROL_PAYLOAD: usize = 125;
 
 #[derive(Debug)]
 pub struct DataFrame {
@@ -48,49 +53,52 @@ impl fmt::Display for DataFrame {
 impl DataFrame {
     pub fn from_data<T: AsRef<[u8]>>(data: T, opcode: OpCode, mask: bool) -> Option<Self> {
         let data_bytes: &[u8] = data.as_ref();
-        let data_length = data_bytes.len();
 
-        if !opcode.is_valid() {
+        if !opcode.is_valid() || (opcode.is_control() && data_bytes.len() > MAX_CONTROL_PAYLOAD) {
             return None;
         }
 
-        let fin_rscv_opcode: u8 = 0b10000000 | u8::from(opcode);
-        let mut masking_key: Option<[u8; 4]> = None;
-        let mut mask_payload_length: u8 = if mask {
-            let mut random_bytes = [0u8; 4];
-            rand::thread_rng().fill_bytes(&mut random_bytes);
-            masking_key = Some(random_bytes);
-            0b10000000
-        } else {
-            0b0000000
+        let mut frame = DataFrame {
+            fin_rscv_opcode: 0b10000000 | u8::from(opcode),
+            mask_payload_length: 0,
+            extended_payload_length: None,
+            masking_key: None,
+            payload: Vec::new(),
         };
-
-        let mut extended_payload_length: Option<ExtendedPayLoadLength> = None;
-        match data_length {
-            0..=125 => {
-                mask_payload_length = mask_payload_length | data_length as u8;
-            }
-            126..=65535 => {
-                mask_payload_length = mask_payload_length | 126u8;
-                extended_payload_length = Some(ExtendedPayLoadLength::Medium(data_length as u16));
-            }
-            _ => {
-                mask_payload_length = mask_payload_length | 127u8;
-                extended_payload_length = Some(ExtendedPayLoadLength::Large(data_length as u64));
-            }
+        frame.set_payload(data_bytes.to_vec());
+        if mask {
+            frame.mask();
         }
 
-        let mut frame = DataFrame {
-            fin_rscv_opcode,
-            mask_payload_length,
-            extended_payload_length,
-            masking_key,
-            payload: data_bytes.to_vec(),
-        };
+        Some(frame)
+    }
 
-        frame.apply_mask();
+    pub fn fragments<T: AsRef<[u8]>>(
+        data: T,
+        opcode: OpCode,
+        mask: bool,
+        fragment_size: usize,
+    ) -> Option<Vec<Self>> {
+        let data_bytes: &[u8] = data.as_ref();
 
-        Some(frame)
+        // control frames must never be fragmented
+        if opcode.is_control() || fragment_size == 0 || data_bytes.len() <= fragment_size {
+            return Self::from_data(data_bytes, opcode, mask).map(|frame| vec![frame]);
+        }
+
+        let chunk_count = data_bytes.len().div_ceil(fragment_size);
+        let mut frames: Vec<Self> = Vec::with_capacity(chunk_count);
+        let mut frame_opcode = opcode;
+        for (i, chunk) in data_bytes.chunks(fragment_size).enumerate() {
+            let mut frame = Self::from_data(chunk, frame_opcode, mask)?;
+            if i + 1 < chunk_count {
+                frame.unset_final_fragment();
+            }
+            frames.push(frame);
+            frame_opcode = OpCode::ContinuationFrame;
+        }
+
+        Some(frames)
     }
 
     pub fn is_final_fragment(&self) -> bool {
@@ -105,6 +113,47 @@ impl DataFrame {
         self.fin_rscv_opcode = self.fin_rscv_opcode & 0b01111111;
     }
 
+    pub fn is_rsv1(&self) -> bool {
+        ((self.fin_rscv_opcode >> 6) & 1) != 0
+    }
+
+    pub fn set_rsv1(&mut self) {
+        self.fin_rscv_opcode |= 0b01000000;
+    }
+
+    pub fn unset_rsv1(&mut self) {
+        self.fin_rscv_opcode &= 0b10111111;
+    }
+
+    pub fn is_rsv2(&self) -> bool {
+        ((self.fin_rscv_opcode >> 5) & 1) != 0
+    }
+
+    pub fn set_rsv2(&mut self) {
+        self.fin_rscv_opcode |= 0b00100000;
+    }
+
+    pub fn unset_rsv2(&mut self) {
+        self.fin_rscv_opcode &= 0b11011111;
+    }
+
+    pub fn is_rsv3(&self) -> bool {
+        ((self.fin_rscv_opcode >> 4) & 1) != 0
+    }
+
+    pub fn set_rsv3(&mut self) {
+        self.fin_rscv_opcode |= 0b00010000;
+    }
+
+    pub fn unset_rsv3(&mut self) {
+        self.fin_rscv_opcode &= 0b11101111;
+    }
+
+    // RSV1-3 in their header positions, compare against `RSV1`, `RSV2` and `RSV3`
+    pub fn get_rsv_bits(&self) -> u8 {
+        self.fin_rscv_opcode & 0b01110000
+    }
+
     pub fn is_masked(&self) -> bool {
         ((self.mask_payload_length >> 7) & 1) != 0
     }
@@ -156,6 +205,58 @@ impl DataFrame {
         length
     }
 
+    // Replaces the payload and updates the length fields to match, the payload
+    // is taken as it should appear after unmasking.
+    pub fn set_payload(&mut self, payload: Vec<u8>) {
+        let data_length = payload.len();
+        let mask_bit = self.mask_payload_length & 0b10000000;
+        match data_length {
+            0..=125 => {
+                self.mask_payload_length = mask_bit | data_length as u8;
+                self.extended_payload_length = None;
+            }
+            126..=65535 => {
+                self.mask_payload_length = mask_bit | 126u8;
+                self.extended_payload_length = Some(ExtendedPayLoadLength::Medium(data_length as u16));
+            }
+            _ => {
+                self.mask_payload_length = mask_bit | 127u8;
+                self.extended_payload_length = Some(ExtendedPayLoadLength::Large(data_length as u64));
+            }
+        }
+        self.payload = payload;
+    }
+
+    // Masks an unmasked frame with a fresh random key.
+    pub fn mask(&mut self) {
+        if self.masking_key.is_some() {
+            return;
+        }
+
+        let mut random_bytes = [0u8; 4];
+        rand::thread_rng().fill_bytes(&mut random_bytes);
+        self.masking_key = Some(random_bytes);
+        self.set_masked();
+        self.apply_mask();
+    }
+
+    pub fn header(&self) -> FrameHeader {
+        FrameHeader {
+            fin: self.is_final_fragment(),
+            rsv_bits: self.get_rsv_bits(),
+            opcode: self.get_opcode(),
+            payload_length: self.get_payload_length() as u64,
+            masking_key: self.masking_key,
+        }
+    }
+
+    // Writes the frame as it goes on the wire without building it in a buffer
+    // first, the payload is already masked so it is written as it is.
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> io::Result<()> {
+        let (header, header_size) = self.header().encode();
+        write_all_vectored(writer, &mut [IoSlice::new(&header[..header_size]), IoSlice::new(&self.payload)])
+    }
+
     pub fn apply_mask(&mut self) {
         if let Some(masking_key) = self.masking_key {
             for (i, byte) in self.payload.iter_mut().enumerate() {
@@ -169,6 +270,20 @@ impl TryFrom<&[u8]> for DataFrame {
     type Error = RsError;
 
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        DataFrame::parse(data).map(|(frame, _)| frame)
+    }
+}
+
+impl DataFrame {
+    // Parses a single frame from the start of `data` and returns it together with
+    // the number of bytes it occupied, any trailing bytes belong to the next frame.
+    pub fn parse(data: &[u8]) -> RsResult<(Self, usize)> {
+        Self::parse_with_limit(data, usize::MAX)
+    }
+
+    // Like `parse` but fails with `FrameTooLarge` as soon as the header announces
+    // a payload over `max_payload_size`, without waiting for the payload itself.
+    pub fn parse_with_limit(data: &[u8], max_payload_size: usize) -> RsResult<(Self, usize)> {
         if data.len() < 2 {
             return Err(RsError::IncompleteData);
         }
@@ -176,14 +291,22 @@ impl TryFrom<&[u8]> for DataFrame {
         let fin_rscv_opcode = data[0];
         let mask_payload_length = data[1];
 
-        if (fin_rscv_opcode >> 7) & 1 != 1 {
-            return Err(RsError::FragmentationNotSupported);
-        }
-        if (fin_rscv_opcode & 0b00001111) > 0xA {
+        // opcodes 3-7 and 0xB-0xF are reserved
+        let opcode = OpCode::from(fin_rscv_opcode & 0b00001111);
+        if !opcode.is_valid() {
             return Err(RsError::InvalidOpCode);
         }
 
         let payload_length_indicator: u8 = data[1] & 0b01111111;
+        if opcode.is_control() {
+            if fin_rscv_opcode & 0b10000000 == 0 {
+                return Err(RsError::FragmentedControlFrame);
+            }
+            if payload_length_indicator as usize > MAX_CONTROL_PAYLOAD {
+                return Err(RsError::ControlFrameTooLarge);
+            }
+        }
+
         let extended_payload_length = match payload_length_indicator {
             126 => {
                 if data.len() < 4 {
@@ -191,7 +314,11 @@ impl TryFrom<&[u8]> for DataFrame {
                 }
                 let mut bytes = [0u8; 2];
                 bytes.copy_from_slice(&data[2..=3]);
-                Some(ExtendedPayLoadLength::Medium(u16::from_be_bytes(bytes)))
+                let length = u16::from_be_bytes(bytes);
+                if length < 126 {
+                    return Err(RsError::NonMinimalLength);
+                }
+                Some(ExtendedPayLoadLength::Medium(length))
             }
             127 => {
                 if data.len() < 10 {
@@ -199,11 +326,28 @@ impl TryFrom<&[u8]> for DataFrame {
                 }
                 let mut bytes = [0u8; 8];
                 bytes.copy_from_slice(&data[2..10]);
-                Some(ExtendedPayLoadLength::Large(u64::from_be_bytes(bytes)))
+                let length = u64::from_be_bytes(bytes);
+                if length >> 63 != 0 {
+                    return Err(RsError::InvalidPayloadLength);
+                }
+                if length <= u16::MAX as u64 {
+                    return Err(RsError::NonMinimalLength);
+                }
+                Some(ExtendedPayLoadLength::Large(length))
             }
             _ => None,
         };
 
+        let payload_length = match extended_payload_length {
+            Some(ExtendedPayLoadLength::Medium(len)) => len as u64,
+            Some(ExtendedPayLoadLength::Large(len)) => len,
+            None => payload_length_indicator as u64,
+        };
+        if payload_length > max_payload_size as u64 {
+            return Err(RsError::FrameTooLarge);
+        }
+        let payload_length = payload_length as usize;
+
         let mut payload_start = match extended_payload_length {
             Some(ExtendedPayLoadLength::Medium(_)) => 4,
             Some(ExtendedPayLoadLength::Large(_)) => 10,
@@ -223,17 +367,12 @@ impl TryFrom<&[u8]> for DataFrame {
             None
         };
 
-        let payload_length = match extended_payload_length {
-            Some(ExtendedPayLoadLength::Medium(len)) => len as usize,
-            Some(ExtendedPayLoadLength::Large(len)) => len as usize,
-            None => payload_length_indicator as usize,
-        };
-
         if data.len() < payload_start + payload_length {
             return Err(RsError::IncompleteData);
         }
 
-        let payload = data[payload_start..payload_start + payload_length].to_vec();
+        let frame_end = payload_start + payload_length;
+        let payload = data[payload_start..frame_end].to_vec();
 
         let mut frame = DataFrame {
             fin_rscv_opcode,
@@ -245,7 +384,7 @@ impl TryFrom<&[u8]> for DataFrame {
 
         frame.apply_mask();
 
-        Ok(frame)
+        Ok((frame, frame_end))
     }
 }
 
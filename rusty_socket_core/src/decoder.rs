@@ -0,0 +1,86 @@
+use crate::{DataFrame, Role, RsError, RsResult};
+
+pub struct FrameDecoder {
+    buffer: Vec<u8>,
+    // RSV bits claimed by negotiated extensions, any other bit fails the frame
+    reserved_bits: u8,
+    // our side of the connection, decides whether incoming frames must be masked
+    role: Option<Role>,
+    // frames announcing a larger payload fail with 1009 before it is buffered
+    max_frame_size: usize,
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        FrameDecoder {
+            buffer: Vec::new(),
+            reserved_bits: 0,
+            role: None,
+            max_frame_size: usize::MAX,
+        }
+    }
+
+    // A decoder for the given side of a connection, on top of the framing rules it
+    // rejects server frames that are masked and client frames that are not.
+    pub fn with_role(role: Role) -> Self {
+        FrameDecoder {
+            role: Some(role),
+            ..FrameDecoder::new()
+        }
+    }
+
+    pub fn set_reserved_bits(&mut self, reserved_bits: u8) {
+        self.reserved_bits = reserved_bits;
+    }
+
+    pub fn set_max_frame_size(&mut self, max_frame_size: usize) {
+        self.max_frame_size = max_frame_size;
+    }
+
+    // Appends the bytes from a single read and returns every frame that is now
+    // complete, bytes of a trailing partial frame stay buffered for the next feed.
+    pub fn feed(&mut self, data: &[u8]) -> RsResult<Vec<DataFrame>> {
+        self.extend(data);
+
+        let mut frames = Vec::new();
+        while let Some(frame) = self.next_frame()? {
+            frames.push(frame);
+        }
+
+        Ok(frames)
+    }
+
+    // Buffers bytes without decoding them, frames are pulled out with `next_frame`.
+    pub fn extend(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    pub fn next_frame(&mut self) -> RsResult<Option<DataFrame>> {
+        match DataFrame::parse_with_limit(&self.buffer, self.max_frame_size) {
+            Ok((frame, consumed)) => {
+                self.buffer.drain(..consumed);
+                if frame.get_rsv_bits() & !self.reserved_bits != 0 {
+                    return Err(RsError::ReservedBitsSet);
+                }
+                match self.role {
+                    Some(Role::Server) if !frame.is_masked() => return Err(RsError::UnmaskedFrame),
+                    Some(Role::Client) if frame.is_masked() => return Err(RsError::MaskedFrame),
+                    _ => {}
+                }
+                Ok(Some(frame))
+            }
+            Err(RsError::IncompleteData) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+}
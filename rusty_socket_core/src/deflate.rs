@@ -0,0 +1,455 @@
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::{DataFrame, Extension, OpCode, Role, RsError, RsResult, RSV1};
+
+pub const EXTENSION_NAME: &str = "permessage-deflate";
+
+// every compressed message ends with this empty stored block, it is stripped
+// before sending and put back before inflating (RFC 7692 section 7.2.1)
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+// zlib cannot produce raw deflate streams with a 256 byte window
+const MIN_WINDOW_BITS: u8 = 9;
+const MAX_WINDOW_BITS: u8 = 15;
+
+// Parameters of permessage-deflate. On the server these are the limits it is
+// willing to accept, on the client what it offers, and after the handshake the
+// values both sides agreed on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeflateConfig {
+    pub server_no_context_takeover: bool,
+    pub client_no_context_takeover: bool,
+    pub server_max_window_bits: u8,
+    pub client_max_window_bits: u8,
+}
+
+impl Default for DeflateConfig {
+    fn default() -> Self {
+        DeflateConfig {
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+            server_max_window_bits: MAX_WINDOW_BITS,
+            client_max_window_bits: MAX_WINDOW_BITS,
+        }
+    }
+}
+
+#[derive(Default)]
+struct DeflateParams {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    server_max_window_bits: Option<u8>,
+    // the client may offer this parameter without a value
+    client_max_window_bits: Option<Option<u8>>,
+}
+
+impl DeflateParams {
+    // Parses the parameters of one extension entry, `None` if the entry is not
+    // permessage-deflate or carries unknown, repeated or malformed parameters.
+    fn parse(extension: &str) -> Option<Self> {
+        let mut parts = extension.split(';').map(str::trim);
+        if parts.next()? != EXTENSION_NAME {
+            return None;
+        }
+
+        let mut params = DeflateParams::default();
+        for part in parts {
+            let (name, value) = match part.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (part, None),
+            };
+            match (name, value) {
+                ("server_no_context_takeover", None) if !params.server_no_context_takeover => {
+                    params.server_no_context_takeover = true;
+                }
+                ("client_no_context_takeover", None) if !params.client_no_context_takeover => {
+                    params.client_no_context_takeover = true;
+                }
+                ("server_max_window_bits", Some(value)) if params.server_max_window_bits.is_none() => {
+                    params.server_max_window_bits = Some(Self::window_bits(value)?);
+                }
+                ("client_max_window_bits", value) if params.client_max_window_bits.is_none() => {
+                    let bits = match value {
+                        Some(value) => Some(Self::window_bits(value)?),
+                        None => None,
+                    };
+                    params.client_max_window_bits = Some(bits);
+                }
+                _ => return None,
+            }
+        }
+
+        Some(params)
+    }
+
+    // RFC 7692 allows 8 but zlib cannot use a window that small, offers asking
+    // for it are skipped on both sides.
+    fn window_bits(value: &str) -> Option<u8> {
+        match value.parse::<u8>() {
+            Ok(bits) if (MIN_WINDOW_BITS..=MAX_WINDOW_BITS).contains(&bits) && !value.starts_with('0') => Some(bits),
+            _ => None,
+        }
+    }
+}
+
+impl DeflateConfig {
+    // Server side: picks the first offer in a `Sec-WebSocket-Extensions` header
+    // that fits these limits and returns the agreed parameters.
+    pub fn negotiate(&self, extensions: &str) -> Option<DeflateConfig> {
+        for offer in extensions.split(',') {
+            let params = match DeflateParams::parse(offer) {
+                Some(params) => params,
+                None => continue,
+            };
+
+            let server_max_window_bits = params
+                .server_max_window_bits
+                .unwrap_or(MAX_WINDOW_BITS)
+                .min(self.server_max_window_bits.max(MIN_WINDOW_BITS));
+
+            // a window limit only binds the client if it said it supports one
+            let client_max_window_bits = match params.client_max_window_bits {
+                Some(bits) => bits.unwrap_or(MAX_WINDOW_BITS).min(self.client_max_window_bits.max(MIN_WINDOW_BITS)),
+                None => MAX_WINDOW_BITS,
+            };
+
+            return Some(DeflateConfig {
+                server_no_context_takeover: params.server_no_context_takeover || self.server_no_context_takeover,
+                client_no_context_takeover: params.client_no_context_takeover || self.client_no_context_takeover,
+                server_max_window_bits,
+                client_max_window_bits,
+            });
+        }
+
+        None
+    }
+
+    // Client side: checks the server's answer against what we offered.
+    pub fn accept_response(&self, extensions: &str) -> RsResult<DeflateConfig> {
+        if extensions.contains(',') {
+            return Err(RsError::ProtocolError);
+        }
+        let params = DeflateParams::parse(extensions).ok_or(RsError::ProtocolError)?;
+
+        let server_max_window_bits = params.server_max_window_bits.unwrap_or(MAX_WINDOW_BITS);
+        if server_max_window_bits > self.server_max_window_bits {
+            return Err(RsError::ProtocolError);
+        }
+
+        let client_max_window_bits = match params.client_max_window_bits {
+            Some(Some(bits)) => bits.min(self.client_max_window_bits),
+            Some(None) => return Err(RsError::ProtocolError),
+            None => self.client_max_window_bits,
+        };
+        if client_max_window_bits < MIN_WINDOW_BITS {
+            return Err(RsError::ProtocolError);
+        }
+
+        Ok(DeflateConfig {
+            server_no_context_takeover: params.server_no_context_takeover,
+            client_no_context_takeover: params.client_no_context_takeover || self.client_no_context_takeover,
+            server_max_window_bits,
+            client_max_window_bits,
+        })
+    }
+
+    // The parameters a server agreed to, read back from the entry of its response
+    // header, `None` if the entry is not a valid permessage-deflate response.
+    pub fn from_response_header(entry: &str) -> Option<DeflateConfig> {
+        let params = DeflateParams::parse(entry)?;
+        Some(DeflateConfig {
+            server_no_context_takeover: params.server_no_context_takeover,
+            client_no_context_takeover: params.client_no_context_takeover,
+            server_max_window_bits: params.server_max_window_bits.unwrap_or(MAX_WINDOW_BITS),
+            client_max_window_bits: params.client_max_window_bits.flatten().unwrap_or(MAX_WINDOW_BITS),
+        })
+    }
+
+    // Value for the client's `Sec-WebSocket-Extensions` request header.
+    pub fn offer_header(&self) -> String {
+        let mut header = String::from(EXTENSION_NAME);
+        if self.server_no_context_takeover {
+            header.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            header.push_str("; client_no_context_takeover");
+        }
+        if self.server_max_window_bits < MAX_WINDOW_BITS {
+            header.push_str(&format!("; server_max_window_bits={}", self.server_max_window_bits));
+        }
+        header.push_str("; client_max_window_bits");
+        if self.client_max_window_bits < MAX_WINDOW_BITS {
+            header.push_str(&format!("={}", self.client_max_window_bits));
+        }
+
+        header
+    }
+
+    // Value for the server's `Sec-WebSocket-Extensions` response header.
+    pub fn response_header(&self) -> String {
+        let mut header = String::from(EXTENSION_NAME);
+        if self.server_no_context_takeover {
+            header.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            header.push_str("; client_no_context_takeover");
+        }
+        if self.server_max_window_bits < MAX_WINDOW_BITS {
+            header.push_str(&format!("; server_max_window_bits={}", self.server_max_window_bits));
+        }
+        if self.client_max_window_bits < MAX_WINDOW_BITS {
+            header.push_str(&format!("; client_max_window_bits={}", self.client_max_window_bits));
+        }
+
+        header
+    }
+
+    pub fn encoder(&self, role: Role) -> DeflateEncoder {
+        let (window_bits, no_context_takeover) = match role {
+            Role::Server => (self.server_max_window_bits, self.server_no_context_takeover),
+            Role::Client => (self.client_max_window_bits, self.client_no_context_takeover),
+        };
+
+        DeflateEncoder {
+            compressor: Compress::new_with_window_bits(
+                Compression::default(),
+                false,
+                window_bits.clamp(MIN_WINDOW_BITS, MAX_WINDOW_BITS),
+            ),
+            no_context_takeover,
+        }
+    }
+
+    pub fn decoder(&self, role: Role) -> DeflateDecoder {
+        let peer_no_context_takeover = match role {
+            Role::Server => self.client_no_context_takeover,
+            Role::Client => self.server_no_context_takeover,
+        };
+
+        // the largest window inflates anything the peer may produce
+        DeflateDecoder {
+            decompressor: Decompress::new_with_window_bits(false, MAX_WINDOW_BITS),
+            peer_no_context_takeover,
+        }
+    }
+}
+
+pub struct DeflateEncoder {
+    compressor: Compress,
+    no_context_takeover: bool,
+}
+
+impl DeflateEncoder {
+    pub fn compress(&mut self, payload: &[u8]) -> RsResult<Vec<u8>> {
+        self.compress_frame(payload, true)
+    }
+
+    // Compresses one fragment of a message, the output of all fragments together
+    // forms the compressed message and is only flushed by the final one.
+    pub fn compress_frame(&mut self, payload: &[u8], is_final: bool) -> RsResult<Vec<u8>> {
+        let flush = if is_final { FlushCompress::Sync } else { FlushCompress::None };
+        let mut output: Vec<u8> = Vec::with_capacity(payload.len() / 2 + 64);
+        let mut input = payload;
+        loop {
+            if output.len() == output.capacity() {
+                output.reserve(output.capacity().max(64));
+            }
+            let consumed_before = self.compressor.total_in();
+            self.compressor
+                .compress_vec(input, &mut output, flush)
+                .map_err(|_| RsError::CompressionError)?;
+            input = &input[(self.compressor.total_in() - consumed_before) as usize..];
+
+            // the flush is complete once all input is in and space is left over
+            if input.is_empty() && output.len() < output.capacity() {
+                break;
+            }
+        }
+
+        if is_final {
+            if output.ends_with(&DEFLATE_TAIL) {
+                output.truncate(output.len() - DEFLATE_TAIL.len());
+            }
+            if self.no_context_takeover {
+                self.compressor.reset();
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+pub struct DeflateDecoder {
+    decompressor: Decompress,
+    peer_no_context_takeover: bool,
+}
+
+impl DeflateDecoder {
+    pub fn decompress(&mut self, payload: &[u8]) -> RsResult<Vec<u8>> {
+        self.decompress_frame(payload, true)
+    }
+
+    // Inflates one fragment of a compressed message, the tail is added back
+    // after the final one.
+    pub fn decompress_frame(&mut self, payload: &[u8], is_final: bool) -> RsResult<Vec<u8>> {
+        self.decompress_frame_limited(payload, is_final, usize::MAX)
+    }
+
+    // Like `decompress_frame` but gives up with `MessageTooLarge` as soon as the
+    // output would grow past `max_size`, so a small frame cannot inflate without bound.
+    pub fn decompress_frame_limited(&mut self, payload: &[u8], is_final: bool, max_size: usize) -> RsResult<Vec<u8>> {
+        let mut compressed = Vec::with_capacity(payload.len() + DEFLATE_TAIL.len());
+        compressed.extend_from_slice(payload);
+        if is_final {
+            compressed.extend_from_slice(&DEFLATE_TAIL);
+        }
+
+        // one byte over the limit is enough to tell that it was exceeded
+        let max_capacity = max_size.saturating_add(1);
+        let mut output: Vec<u8> = Vec::with_capacity((payload.len() * 2 + 64).min(max_capacity));
+        let mut input = &compressed[..];
+        let mut stream_ended = false;
+        loop {
+            if output.len() > max_size {
+                return Err(RsError::MessageTooLarge);
+            }
+            if output.len() == output.capacity() {
+                output.reserve(output.capacity().min(max_capacity - output.len()));
+            }
+            let consumed_before = self.decompressor.total_in();
+            let produced_before = output.len();
+            let status = self
+                .decompressor
+                .decompress_vec(input, &mut output, FlushDecompress::Sync)
+                .map_err(|_| RsError::CompressionError)?;
+            let consumed = (self.decompressor.total_in() - consumed_before) as usize;
+            input = &input[consumed..];
+
+            // a final block ends the stream, the next message starts a fresh one
+            if status == Status::StreamEnd {
+                stream_ended = true;
+                break;
+            }
+            if input.is_empty() && output.len() < output.capacity() {
+                break;
+            }
+            if consumed == 0 && output.len() == produced_before && output.len() < output.capacity() {
+                return Err(RsError::CompressionError);
+            }
+        }
+        if output.len() > max_size {
+            return Err(RsError::MessageTooLarge);
+        }
+
+        if stream_ended || (is_final && self.peer_no_context_takeover) {
+            self.decompressor.reset(false);
+        }
+
+        Ok(output)
+    }
+}
+
+// permessage-deflate as an `Extension`. Before the handshake `config` holds the
+// limits or offer, afterwards the agreed parameters.
+pub struct PerMessageDeflate {
+    config: DeflateConfig,
+    role: Role,
+    encoder: Option<DeflateEncoder>,
+    decoder: Option<DeflateDecoder>,
+    // set while the fragments of a compressed message are arriving
+    inflating: bool,
+}
+
+impl PerMessageDeflate {
+    pub fn new(config: DeflateConfig, role: Role) -> Self {
+        PerMessageDeflate {
+            config,
+            role,
+            encoder: None,
+            decoder: None,
+            inflating: false,
+        }
+    }
+
+    pub fn config(&self) -> &DeflateConfig {
+        &self.config
+    }
+
+    fn start(&mut self, agreed: DeflateConfig) {
+        self.encoder = Some(agreed.encoder(self.role));
+        self.decoder = Some(agreed.decoder(self.role));
+        self.config = agreed;
+    }
+}
+
+impl Extension for PerMessageDeflate {
+    fn name(&self) -> &str {
+        EXTENSION_NAME
+    }
+
+    fn rsv_bits(&self) -> u8 {
+        RSV1
+    }
+
+    fn offer(&self) -> String {
+        self.config.offer_header()
+    }
+
+    fn negotiate(&mut self, offer: &str) -> Option<String> {
+        let agreed = self.config.negotiate(offer)?;
+        self.start(agreed);
+
+        Some(agreed.response_header())
+    }
+
+    fn accept(&mut self, response: &str) -> RsResult<()> {
+        let agreed = self.config.accept_response(response)?;
+        self.start(agreed);
+
+        Ok(())
+    }
+
+    // Compresses the message across its fragments and flags the first one with RSV1.
+    fn encode(&mut self, mut frames: Vec<DataFrame>) -> RsResult<Vec<DataFrame>> {
+        let encoder = match self.encoder.as_mut() {
+            Some(encoder) => encoder,
+            None => return Ok(frames),
+        };
+
+        for (i, frame) in frames.iter_mut().enumerate() {
+            let compressed = encoder.compress_frame(&frame.payload, frame.is_final_fragment())?;
+            frame.set_payload(compressed);
+            if i == 0 {
+                frame.set_rsv1();
+            }
+        }
+
+        Ok(frames)
+    }
+
+    fn decode(&mut self, frame: DataFrame) -> RsResult<DataFrame> {
+        self.decode_limited(frame, usize::MAX)
+    }
+
+    fn decode_limited(&mut self, mut frame: DataFrame, max_size: usize) -> RsResult<DataFrame> {
+        // only the first frame of a message says whether it is compressed
+        match frame.get_opcode() {
+            OpCode::ContinuationFrame if frame.is_rsv1() => return Err(RsError::ReservedBitsSet),
+            OpCode::ContinuationFrame => {}
+            _ => self.inflating = frame.is_rsv1(),
+        }
+        if !self.inflating {
+            return Ok(frame);
+        }
+
+        let decoder = self.decoder.as_mut().ok_or(RsError::ReservedBitsSet)?;
+        let is_final = frame.is_final_fragment();
+        let payload = decoder.decompress_frame_limited(&frame.payload, is_final, max_size)?;
+        frame.set_payload(payload);
+        frame.unset_rsv1();
+        if is_final {
+            self.inflating = false;
+        }
+
+        Ok(frame)
+    }
+}
@@ -1,4 +1,6 @@
-use std::fmt;
+use std::{fmt, io};
+
+use crate::CloseCode;
 
 pub type RsResult<T> = Result<T, RsError>;
 
@@ -10,8 +12,23 @@ pub enum RsError {
     UnprocessableContent,
     UpgradeRequired,
     IncompleteData,
-    FragmentationNotSupported,
     InvalidOpCode,
+    ControlFrameTooLarge,
+    FragmentedControlFrame,
+    UnmaskedFrame,
+    MaskedFrame,
+    InvalidPayloadLength,
+    NonMinimalLength,
+    FrameTooLarge,
+    MessageTooLarge,
+    UnexpectedContinuation,
+    UnfinishedMessage,
+    InvalidCloseCode,
+    InvalidUtf8,
+    ReservedBitsSet,
+    CompressionError,
+    ConnectionClosed,
+    IoError(io::Error),
 }
 
 impl fmt::Display for RsError {
@@ -23,10 +40,42 @@ impl fmt::Display for RsError {
             RsError::UnprocessableContent => write!(f, "Unprocessable Content"),
             RsError::UpgradeRequired => write!(f, "Upgrade Required"),
             RsError::IncompleteData => write!(f, "Insufficient Data"),
-            RsError::FragmentationNotSupported => write!(f, "Fragmentation Not yet Supported"),
             RsError::InvalidOpCode => write!(f, "Invalid Opcode"),
+            RsError::ControlFrameTooLarge => write!(f, "Control Frame Payload Over 125 Bytes"),
+            RsError::FragmentedControlFrame => write!(f, "Fragmented Control Frame"),
+            RsError::UnmaskedFrame => write!(f, "Unmasked Frame From Client"),
+            RsError::MaskedFrame => write!(f, "Masked Frame From Server"),
+            RsError::InvalidPayloadLength => write!(f, "Payload Length Has Most Significant Bit Set"),
+            RsError::NonMinimalLength => write!(f, "Payload Length Not Minimally Encoded"),
+            RsError::FrameTooLarge => write!(f, "Frame Exceeds Maximum Size"),
+            RsError::MessageTooLarge => write!(f, "Message Exceeds Maximum Size"),
+            RsError::UnexpectedContinuation => write!(f, "Continuation Frame Without Message"),
+            RsError::UnfinishedMessage => write!(f, "New Message Before Previous Finished"),
+            RsError::InvalidCloseCode => write!(f, "Invalid Close Code"),
+            RsError::InvalidUtf8 => write!(f, "Invalid UTF-8 Data"),
+            RsError::ReservedBitsSet => write!(f, "Reserved Bits Set Without Extension"),
+            RsError::CompressionError => write!(f, "Invalid Compressed Data"),
+            RsError::ConnectionClosed => write!(f, "Connection Closed"),
+            RsError::IoError(e) => write!(f, "I/O Error: {}", e),
         }
     }
 }
 
+impl RsError {
+    // Close code to send when this error ends a connection.
+    pub fn close_code(&self) -> CloseCode {
+        match self {
+            RsError::InvalidUtf8 => CloseCode::InvalidPayload,
+            RsError::FrameTooLarge | RsError::MessageTooLarge => CloseCode::MessageTooBig,
+            _ => CloseCode::ProtocolError,
+        }
+    }
+}
+
+impl From<io::Error> for RsError {
+    fn from(error: io::Error) -> Self {
+        RsError::IoError(error)
+    }
+}
+
 impl std::error::Error for RsError {}
@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use crate::{DataFrame, Message, RsError, RsResult};
+
+// reserved bits in their position in the first header byte
+pub const RSV1: u8 = 0b01000000;
+pub const RSV2: u8 = 0b00100000;
+pub const RSV3: u8 = 0b00010000;
+
+// An extension negotiated through `Sec-WebSocket-Extensions` (RFC 6455 section 9).
+// Each connection gets its own instance, it is configured by `negotiate` on the
+// server or `accept` on the client and afterwards sees every data frame going
+// out and coming in. Control frames are never passed to extensions.
+pub trait Extension: Send {
+    // token used in the extension header, e.g. `permessage-deflate`
+    fn name(&self) -> &str;
+
+    // RSV bits the extension may set, frames using any other bit are rejected
+    fn rsv_bits(&self) -> u8;
+
+    // Client side: the entry offered in the request header.
+    fn offer(&self) -> String;
+
+    // Server side: called with each offered entry naming this extension until one
+    // is accepted, returns the entry for the response header.
+    fn negotiate(&mut self, offer: &str) -> Option<String>;
+
+    // Client side: applies the entry the server answered with.
+    fn accept(&mut self, response: &str) -> RsResult<()>;
+
+    // Transforms the unmasked frames of one outgoing data message.
+    fn encode(&mut self, frames: Vec<DataFrame>) -> RsResult<Vec<DataFrame>>;
+
+    // Transforms one incoming data frame before it is assembled into a message.
+    fn decode(&mut self, frame: DataFrame) -> RsResult<DataFrame>;
+
+    // Like `decode` but fails with `MessageTooLarge` rather than produce a payload
+    // over `max_size`. Extensions that inflate data should check while they work.
+    fn decode_limited(&mut self, frame: DataFrame, max_size: usize) -> RsResult<DataFrame> {
+        let frame = self.decode(frame)?;
+        if frame.payload.len() > max_size {
+            return Err(RsError::MessageTooLarge);
+        }
+        Ok(frame)
+    }
+}
+
+// Builds a fresh extension for every connection.
+pub type ExtensionFactory = Arc<dyn Fn() -> Box<dyn Extension> + Send + Sync>;
+
+// The extensions in use on one connection, in the order they were agreed.
+#[derive(Default)]
+pub struct Extensions {
+    extensions: Vec<Box<dyn Extension>>,
+    header: Vec<String>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Extensions {
+            extensions: Vec::new(),
+            header: Vec::new(),
+        }
+    }
+
+    // Server side: accepts every candidate the client offered, skipping those
+    // whose RSV bits clash with an extension accepted before them.
+    pub fn negotiate(candidates: Vec<Box<dyn Extension>>, offers: &str) -> Self {
+        let mut extensions = Extensions::new();
+        for mut candidate in candidates {
+            if candidate.rsv_bits() & extensions.rsv_bits() != 0 {
+                continue;
+            }
+
+            let name = candidate.name().to_string();
+            let response = offers
+                .split(',')
+                .map(str::trim)
+                .filter(|offer| entry_name(offer) == name)
+                .find_map(|offer| candidate.negotiate(offer));
+            if let Some(response) = response {
+                extensions.header.push(response);
+                extensions.extensions.push(candidate);
+            }
+        }
+
+        extensions
+    }
+
+    // Client side: value for the request header, `None` when nothing is offered.
+    pub fn offer_header(candidates: &[Box<dyn Extension>]) -> Option<String> {
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let offers: Vec<String> = candidates.iter().map(|candidate| candidate.offer()).collect();
+        Some(offers.join(", "))
+    }
+
+    // Client side: checks the server's answer, it may only use extensions we
+    // offered and each of them at most once.
+    pub fn accept(mut candidates: Vec<Box<dyn Extension>>, response: &str) -> RsResult<Self> {
+        let mut extensions = Extensions::new();
+        for entry in response.split(',').map(str::trim) {
+            let position = candidates
+                .iter()
+                .position(|candidate| candidate.name() == entry_name(entry))
+                .ok_or(RsError::ProtocolError)?;
+            let mut extension = candidates.remove(position);
+            if extension.rsv_bits() & extensions.rsv_bits() != 0 {
+                return Err(RsError::ProtocolError);
+            }
+
+            extension.accept(entry)?;
+            extensions.header.push(entry.to_string());
+            extensions.extensions.push(extension);
+        }
+
+        Ok(extensions)
+    }
+
+    // Value for the response header, `None` when nothing was agreed.
+    pub fn header(&self) -> Option<String> {
+        if self.header.is_empty() {
+            return None;
+        }
+
+        Some(self.header.join(", "))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.extensions.iter().map(|extension| extension.name()).collect()
+    }
+
+    pub fn rsv_bits(&self) -> u8 {
+        self.extensions.iter().fold(0, |bits, extension| bits | extension.rsv_bits())
+    }
+
+    pub fn encode(&mut self, frames: Vec<DataFrame>) -> RsResult<Vec<DataFrame>> {
+        self.extensions
+            .iter_mut()
+            .try_fold(frames, |frames, extension| extension.encode(frames))
+    }
+
+    // Undoes the extensions in the reverse order of `encode`, none of them may
+    // produce a payload over `max_size`.
+    pub fn decode(&mut self, frame: DataFrame, max_size: usize) -> RsResult<DataFrame> {
+        self.extensions
+            .iter_mut()
+            .rev()
+            .try_fold(frame, |frame, extension| extension.decode_limited(frame, max_size))
+    }
+
+    // Like `Message::to_frames` but data messages pass through every extension
+    // before they are masked, control messages are sent as they are.
+    pub fn to_frames(&mut self, message: &Message, mask: bool, fragment_size: usize) -> RsResult<Vec<DataFrame>> {
+        if message.is_control() || self.is_empty() {
+            return message.to_frames(mask, fragment_size).ok_or(RsError::InvalidOpCode);
+        }
+
+        let frames = message.to_frames(false, fragment_size).ok_or(RsError::InvalidOpCode)?;
+        let mut frames = self.encode(frames)?;
+        if mask {
+            frames.iter_mut().for_each(DataFrame::mask);
+        }
+
+        Ok(frames)
+    }
+}
+
+fn entry_name(entry: &str) -> &str {
+    entry.split(';').next().unwrap_or("").trim()
+}
@@ -0,0 +1,151 @@
+use rand::RngCore;
+use std::io::{self, ErrorKind, IoSlice, Write};
+
+use crate::OpCode;
+
+// 2 fixed bytes, 8 for the longest length and 4 for the masking key
+pub const MAX_HEADER_SIZE: usize = 14;
+
+// a multiple of 4 so every chunk starts at the first byte of the masking key
+pub(crate) const MASK_CHUNK_SIZE: usize = 4096;
+
+// The header of one frame on its own, so a payload can be written straight from
+// the caller's buffer instead of being copied into a `DataFrame` first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameHeader {
+    pub fin: bool,
+    // RSV1-3 in their header positions, compare against `RSV1`, `RSV2` and `RSV3`
+    pub rsv_bits: u8,
+    pub opcode: OpCode,
+    pub payload_length: u64,
+    // the payload is masked with this key while it is written
+    pub masking_key: Option<[u8; 4]>,
+}
+
+impl FrameHeader {
+    // Header of a final, unmasked frame.
+    pub fn new(opcode: OpCode, payload_length: usize) -> Self {
+        FrameHeader {
+            fin: true,
+            rsv_bits: 0,
+            opcode,
+            payload_length: payload_length as u64,
+            masking_key: None,
+        }
+    }
+
+    // Picks a fresh random masking key.
+    pub fn mask(&mut self) {
+        let mut masking_key = [0u8; 4];
+        rand::thread_rng().fill_bytes(&mut masking_key);
+        self.masking_key = Some(masking_key);
+    }
+
+    pub fn size(&self) -> usize {
+        let length_size = match self.payload_length {
+            0..=125 => 0,
+            126..=65535 => 2,
+            _ => 8,
+        };
+
+        2 + length_size + if self.masking_key.is_some() { 4 } else { 0 }
+    }
+
+    // The header bytes, only the first `size()` of them are used.
+    pub fn encode(&self) -> ([u8; MAX_HEADER_SIZE], usize) {
+        let mut bytes = [0u8; MAX_HEADER_SIZE];
+        bytes[0] = (u8::from(self.opcode) & 0b00001111) | (self.rsv_bits & 0b01110000);
+        if self.fin {
+            bytes[0] |= 0b10000000;
+        }
+
+        let mut size = 2;
+        match self.payload_length {
+            0..=125 => bytes[1] = self.payload_length as u8,
+            126..=65535 => {
+                bytes[1] = 126;
+                bytes[2..4].copy_from_slice(&(self.payload_length as u16).to_be_bytes());
+                size += 2;
+            }
+            _ => {
+                bytes[1] = 127;
+                bytes[2..10].copy_from_slice(&self.payload_length.to_be_bytes());
+                size += 8;
+            }
+        }
+
+        if let Some(masking_key) = self.masking_key {
+            bytes[1] |= 0b10000000;
+            bytes[size..size + 4].copy_from_slice(&masking_key);
+            size += 4;
+        }
+
+        (bytes, size)
+    }
+
+    // Writes the header and then streams the payload after it, a masked payload
+    // is masked a few kilobytes at a time so it is never copied as a whole.
+    pub fn encode_into<W: Write + ?Sized>(&self, payload: &[u8], writer: &mut W) -> io::Result<()> {
+        self.check_length(payload)?;
+        let (header, header_size) = self.encode();
+        writer.write_all(&header[..header_size])?;
+
+        match self.masking_key {
+            Some(masking_key) => write_masked(writer, payload, masking_key),
+            None => writer.write_all(payload),
+        }
+    }
+
+    // Like `encode_into` but an unmasked frame goes out as a single vectored
+    // write of header and payload, so it usually takes one system call.
+    pub fn write_vectored<W: Write + ?Sized>(&self, payload: &[u8], writer: &mut W) -> io::Result<()> {
+        if self.masking_key.is_some() {
+            return self.encode_into(payload, writer);
+        }
+
+        self.check_length(payload)?;
+        let (header, header_size) = self.encode();
+        write_all_vectored(writer, &mut [IoSlice::new(&header[..header_size]), IoSlice::new(payload)])
+    }
+
+    fn check_length(&self, payload: &[u8]) -> io::Result<()> {
+        if payload.len() as u64 != self.payload_length {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "payload does not match the frame header"));
+        }
+
+        Ok(())
+    }
+}
+
+fn write_masked<W: Write + ?Sized>(writer: &mut W, payload: &[u8], masking_key: [u8; 4]) -> io::Result<()> {
+    let mut buffer = [0u8; MASK_CHUNK_SIZE];
+    for chunk in payload.chunks(MASK_CHUNK_SIZE) {
+        writer.write_all(mask_chunk(chunk, masking_key, &mut buffer))?;
+    }
+
+    Ok(())
+}
+
+// Masks one chunk of at most `MASK_CHUNK_SIZE` bytes into `buffer`.
+pub(crate) fn mask_chunk<'a>(chunk: &[u8], masking_key: [u8; 4], buffer: &'a mut [u8; MASK_CHUNK_SIZE]) -> &'a [u8] {
+    for (i, byte) in chunk.iter().enumerate() {
+        buffer[i] = byte ^ masking_key[i % 4];
+    }
+
+    &buffer[..chunk.len()]
+}
+
+// `Write::write_all_vectored` is not stable yet.
+pub(crate) fn write_all_vectored<W: Write + ?Sized>(writer: &mut W, mut slices: &mut [IoSlice]) -> io::Result<()> {
+    IoSlice::advance_slices(&mut slices, 0);
+    while !slices.is_empty() {
+        match writer.write_vectored(slices) {
+            Ok(0) => return Err(io::Error::new(ErrorKind::WriteZero, "failed to write whole frame")),
+            Ok(written) => IoSlice::advance_slices(&mut slices, written),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(())
+}
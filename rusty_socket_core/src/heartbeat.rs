@@ -0,0 +1,67 @@
+use std::time::{Duration, Instant};
+
+#[derive(Debug, PartialEq)]
+pub enum HeartbeatAction {
+    Idle,
+    SendPing,
+    PeerDead,
+}
+
+pub struct Heartbeat {
+    // a ping goes out once this long has passed since the last one
+    interval: Duration,
+    // the peer counts as dead when the pong has not arrived within this time
+    timeout: Duration,
+    last_ping: Instant,
+    awaiting_pong: bool,
+}
+
+impl Heartbeat {
+    pub fn new(interval: Duration, timeout: Duration) -> Self {
+        Heartbeat {
+            interval,
+            timeout,
+            last_ping: Instant::now(),
+            awaiting_pong: false,
+        }
+    }
+
+    // Tells the caller what to do at `now`, a returned `SendPing` is recorded as sent.
+    pub fn poll(&mut self, now: Instant) -> HeartbeatAction {
+        if now < self.deadline() {
+            return HeartbeatAction::Idle;
+        }
+
+        if self.awaiting_pong {
+            HeartbeatAction::PeerDead
+        } else {
+            self.last_ping = now;
+            self.awaiting_pong = true;
+            HeartbeatAction::SendPing
+        }
+    }
+
+    pub fn pong_received(&mut self) {
+        self.awaiting_pong = false;
+    }
+
+    pub fn is_awaiting_pong(&self) -> bool {
+        self.awaiting_pong
+    }
+
+    // Time left until the next `poll` has something to do, never zero so it can
+    // be handed straight to a socket read timeout.
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.deadline()
+            .saturating_duration_since(now)
+            .max(Duration::from_millis(1))
+    }
+
+    fn deadline(&self) -> Instant {
+        if self.awaiting_pong {
+            self.last_ping + self.timeout
+        } else {
+            self.last_ping + self.interval
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+// Whatever the server's authentication hook attached to a connection, e.g. a
+// user id or a session, it stays with the connection until it closes.
+#[derive(Clone, Default)]
+pub struct Identity {
+    value: Option<Arc<dyn Any + Send + Sync>>,
+}
+
+impl Identity {
+    pub fn new<T: Any + Send + Sync>(value: T) -> Self {
+        Identity {
+            value: Some(Arc::new(value)),
+        }
+    }
+
+    // the identity of connections nobody authenticated
+    pub fn anonymous() -> Self {
+        Identity { value: None }
+    }
+
+    pub fn is_anonymous(&self) -> bool {
+        self.value.is_none()
+    }
+
+    // the attached value, `None` when it is anonymous or of another type
+    pub fn get<T: Any>(&self) -> Option<&T> {
+        self.value.as_ref().and_then(|value| value.downcast_ref())
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.value {
+            Some(_) => write!(f, "Identity(..)"),
+            None => write!(f, "Identity(anonymous)"),
+        }
+    }
+}
@@ -1,11 +1,49 @@
+pub mod assembler;
+#[cfg(feature = "tokio")]
+pub mod async_socket;
+#[cfg(feature = "tokio")]
+pub mod async_stream;
+pub mod close;
 pub mod dataframe;
+pub mod decoder;
+pub mod deflate;
 pub mod errors;
+pub mod extension;
+pub mod frame_header;
+pub mod heartbeat;
+pub mod identity;
+pub mod message;
 pub mod opcode;
+pub mod role;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod transport;
+pub mod utf8;
 pub mod utils;
 pub mod connection_status;
 
+pub use assembler::MessageAssembler;
+#[cfg(feature = "tokio")]
+pub use async_socket::{AsyncReceiver, AsyncSender, AsyncSocket};
+#[cfg(feature = "tokio")]
+pub use async_stream::AsyncStream;
+pub use close::{CloseCode, CloseFrame};
 pub use dataframe::DataFrame;
+pub use decoder::FrameDecoder;
+pub use deflate::{DeflateConfig, DeflateDecoder, DeflateEncoder, PerMessageDeflate};
 pub use errors::{RsError, RsResult};
+pub use extension::{Extension, ExtensionFactory, Extensions, RSV1, RSV2, RSV3};
+pub use frame_header::{FrameHeader, MAX_HEADER_SIZE};
+pub use heartbeat::{Heartbeat, HeartbeatAction};
+pub use identity::Identity;
+pub use message::Message;
 pub use opcode::OpCode;
+pub use role::Role;
+#[cfg(feature = "tls")]
+pub use tls::TlsStream;
+pub use transport::Transport;
+pub use utf8::Utf8Validator;
 pub use connection_status::ConnectionStatus;
 pub use utils::ExtendedPayLoadLength;
+#[cfg(feature = "tls")]
+pub use rustls;
@@ -0,0 +1,165 @@
+use std::fmt;
+use std::io::Write;
+
+use crate::dataframe::MAX_CONTROL_PAYLOAD;
+use crate::{CloseFrame, DataFrame, FrameHeader, OpCode, RsError, RsResult};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    Close(Option<CloseFrame>),
+}
+
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Message::Text(text) => write!(f, "{}", text),
+            Message::Binary(data) => write!(f, "<{} bytes of binary data>", data.len()),
+            Message::Ping(data) => write!(f, "<ping with {} bytes>", data.len()),
+            Message::Pong(data) => write!(f, "<pong with {} bytes>", data.len()),
+            Message::Close(Some(close_frame)) => write!(f, "<close {}>", close_frame),
+            Message::Close(None) => write!(f, "<close>"),
+        }
+    }
+}
+
+impl Message {
+    pub fn from_payload(opcode: OpCode, payload: Vec<u8>) -> RsResult<Self> {
+        match opcode {
+            OpCode::Text => String::from_utf8(payload)
+                .map(Message::Text)
+                .map_err(|_| RsError::InvalidUtf8),
+            OpCode::Binary => Ok(Message::Binary(payload)),
+            OpCode::Ping => Ok(Message::Ping(payload)),
+            OpCode::Pong => Ok(Message::Pong(payload)),
+            OpCode::ConnectionClose => CloseFrame::from_payload(&payload).map(Message::Close),
+            _ => Err(RsError::InvalidOpCode),
+        }
+    }
+
+    pub fn opcode(&self) -> OpCode {
+        match self {
+            Message::Text(_) => OpCode::Text,
+            Message::Binary(_) => OpCode::Binary,
+            Message::Ping(_) => OpCode::Ping,
+            Message::Pong(_) => OpCode::Pong,
+            Message::Close(_) => OpCode::ConnectionClose,
+        }
+    }
+
+    pub fn is_control(&self) -> bool {
+        self.opcode().is_control()
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        match self {
+            Message::Text(text) => text.as_bytes(),
+            Message::Binary(data) | Message::Ping(data) | Message::Pong(data) => data,
+            Message::Close(_) => &[],
+        }
+    }
+
+    pub fn into_payload(self) -> Vec<u8> {
+        match self {
+            Message::Text(text) => text.into_bytes(),
+            Message::Binary(data) | Message::Ping(data) | Message::Pong(data) => data,
+            Message::Close(Some(close_frame)) => close_frame.to_payload(),
+            Message::Close(None) => Vec::new(),
+        }
+    }
+
+    // Encodes the message into one or more frames, only data messages are split
+    // when `fragment_size` is non-zero.
+    pub fn to_frames(&self, mask: bool, fragment_size: usize) -> Option<Vec<DataFrame>> {
+        match self {
+            Message::Close(Some(close_frame)) => close_frame.to_dataframe(mask).map(|frame| vec![frame]),
+            _ => DataFrame::fragments(self.payload(), self.opcode(), mask, fragment_size),
+        }
+    }
+
+    // Writes the same frames as `to_frames` straight from the message's payload,
+    // each fragment is a header followed by a slice of it.
+    pub fn encode_into<W: Write + ?Sized>(&self, writer: &mut W, mask: bool, fragment_size: usize) -> RsResult<()> {
+        if let Message::Close(Some(close_frame)) = self {
+            let frame = close_frame.to_dataframe(mask).ok_or(RsError::ControlFrameTooLarge)?;
+            return Ok(frame.write_to(writer)?);
+        }
+
+        for (header, payload) in self.frame_headers(mask, fragment_size)? {
+            header.write_vectored(payload, writer)?;
+        }
+
+        Ok(())
+    }
+
+    // The header and payload slice of each frame `encode_into` writes, a close
+    // frame with a body is left to `CloseFrame::to_dataframe`. Fails for a ping
+    // or pong over 125 bytes, which the peer would have to reject.
+    pub fn frame_headers(&self, mask: bool, fragment_size: usize) -> RsResult<Vec<(FrameHeader, &[u8])>> {
+        let payload = self.payload();
+        if self.is_control() && payload.len() > MAX_CONTROL_PAYLOAD {
+            return Err(RsError::ControlFrameTooLarge);
+        }
+        // control frames must never be fragmented
+        let fragment_size = match fragment_size {
+            0 => usize::MAX,
+            _ if self.is_control() => usize::MAX,
+            _ => fragment_size,
+        };
+        let chunk_count = payload.len().div_ceil(fragment_size).max(1);
+        let frames = (0..chunk_count)
+            .map(|i| {
+                let start = (i * fragment_size).min(payload.len());
+                let end = start.saturating_add(fragment_size).min(payload.len());
+                let opcode = if i == 0 { self.opcode() } else { OpCode::ContinuationFrame };
+                let mut header = FrameHeader::new(opcode, end - start);
+                header.fin = i + 1 == chunk_count;
+                if mask {
+                    header.mask();
+                }
+                (header, &payload[start..end])
+            })
+            .collect();
+
+        Ok(frames)
+    }
+}
+
+impl From<String> for Message {
+    fn from(value: String) -> Self {
+        Message::Text(value)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(value: &str) -> Self {
+        Message::Text(value.to_string())
+    }
+}
+
+impl From<&String> for Message {
+    fn from(value: &String) -> Self {
+        Message::Text(value.clone())
+    }
+}
+
+impl From<Vec<u8>> for Message {
+    fn from(value: Vec<u8>) -> Self {
+        Message::Binary(value)
+    }
+}
+
+impl From<&[u8]> for Message {
+    fn from(value: &[u8]) -> Self {
+        Message::Binary(value.to_vec())
+    }
+}
+
+impl From<CloseFrame> for Message {
+    fn from(value: CloseFrame) -> Self {
+        Message::Close(Some(value))
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OpCode {
     ContinuationFrame,
     Text,
@@ -16,6 +16,10 @@ impl OpCode {
             _ => true,
         }
     }
+
+    pub fn is_control(&self) -> bool {
+        matches!(self, OpCode::ConnectionClose | OpCode::Ping | OpCode::Pong)
+    }
 }
 
 impl From<u8> for OpCode {
@@ -0,0 +1,12 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    Client,
+    Server,
+}
+
+impl Role {
+    // clients must mask every frame they send, servers must never mask
+    pub fn masks_frames(&self) -> bool {
+        matches!(self, Role::Client)
+    }
+}
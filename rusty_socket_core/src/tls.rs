@@ -0,0 +1,166 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, Connection, ServerConfig, ServerConnection};
+
+use crate::Transport;
+
+// TLS over a blocking TcpStream. The rustls state sits behind a lock that is only
+// held while records are processed, never while waiting on the socket, so a
+// reader blocked in `read` does not stop other clones from writing. Records are
+// taken out under that lock and written after it is released, a separate write
+// lock keeps them in order on the socket.
+pub struct TlsStream {
+    connection: Arc<Mutex<Connection>>,
+    writing: Arc<Mutex<()>>,
+    socket: TcpStream,
+}
+
+impl TlsStream {
+    pub fn connect(config: Arc<ClientConfig>, server_name: &str, socket: TcpStream) -> io::Result<Self> {
+        let server_name = ServerName::try_from(server_name.to_string())
+            .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+        let connection = ClientConnection::new(config, server_name)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+
+        Self::handshake(Connection::from(connection), socket)
+    }
+
+    pub fn accept(config: Arc<ServerConfig>, socket: TcpStream) -> io::Result<Self> {
+        let connection = ServerConnection::new(config)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+
+        Self::handshake(Connection::from(connection), socket)
+    }
+
+    fn handshake(mut connection: Connection, mut socket: TcpStream) -> io::Result<Self> {
+        while connection.is_handshaking() {
+            connection.complete_io(&mut socket)?;
+        }
+
+        Ok(TlsStream {
+            connection: Arc::new(Mutex::new(connection)),
+            writing: Arc::new(Mutex::new(())),
+            socket,
+        })
+    }
+
+    // Sends the records rustls has queued. Always take the write lock before the
+    // session lock.
+    fn write_records(&self) -> io::Result<()> {
+        let _writing = self.writing.lock().unwrap();
+        let records = take_records(&mut self.connection.lock().unwrap())?;
+        (&self.socket).write_all(&records)
+    }
+}
+
+fn take_records(connection: &mut Connection) -> io::Result<Vec<u8>> {
+    let mut records = Vec::new();
+    while connection.wants_write() {
+        connection.write_tls(&mut records)?;
+    }
+
+    Ok(records)
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut record_buffer = [0u8; 4096];
+        loop {
+            {
+                let mut connection = self.connection.lock().unwrap();
+                match connection.reader().read(buf) {
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                    result => return result,
+                }
+            }
+
+            // read timeouts surface here the same way they do on a plain socket
+            let size = (&self.socket).read(&mut record_buffer)?;
+            if size == 0 {
+                return Ok(0);
+            }
+
+            let processed = {
+                let mut connection = self.connection.lock().unwrap();
+                let mut records = &record_buffer[..size];
+                let mut processed = Ok(());
+                while !records.is_empty() && processed.is_ok() {
+                    connection.read_tls(&mut records)?;
+                    processed = connection.process_new_packets().map(|_| ());
+                }
+                processed
+            };
+            if let Err(e) = processed {
+                // send the alert explaining why before giving up
+                let _ = self.write_records();
+                return Err(io::Error::new(ErrorKind::InvalidData, e));
+            }
+            self.write_records()?;
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _writing = self.writing.lock().unwrap();
+        let (size, records) = {
+            let mut connection = self.connection.lock().unwrap();
+            let size = connection.writer().write(buf)?;
+            (size, take_records(&mut connection)?)
+        };
+        (&self.socket).write_all(&records)?;
+
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _writing = self.writing.lock().unwrap();
+        let records = {
+            let mut connection = self.connection.lock().unwrap();
+            connection.writer().flush()?;
+            take_records(&mut connection)?
+        };
+        (&self.socket).write_all(&records)?;
+
+        (&self.socket).flush()
+    }
+}
+
+impl Transport for TlsStream {
+    fn try_clone_boxed(&self) -> io::Result<Box<dyn Transport>> {
+        Ok(Box::new(TlsStream {
+            connection: Arc::clone(&self.connection),
+            writing: Arc::clone(&self.writing),
+            socket: self.socket.try_clone()?,
+        }))
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.socket.set_read_timeout(timeout)
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        // a writer stuck on a client that stopped reading holds the write lock,
+        // the socket is shut down without a close_notify then
+        if let Ok(_writing) = self.writing.try_lock() {
+            let records = {
+                let mut connection = self.connection.lock().unwrap();
+                connection.send_close_notify();
+                take_records(&mut connection)
+            };
+            if let Ok(records) = records {
+                let _ = (&self.socket).write_all(&records);
+            }
+        }
+
+        self.socket.shutdown(how)
+    }
+
+    fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.peer_addr()
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{RsError, RsResult};
+
+// Checks a text message for valid UTF-8 one fragment at a time, so a bad message
+// fails as soon as the offending bytes arrive instead of after the last fragment.
+// A code point split across fragments is held back until its remaining bytes come.
+#[derive(Debug, Default)]
+pub struct Utf8Validator {
+    // start of a code point cut off at the end of the previous fragment, at most 3 bytes
+    pending: Vec<u8>,
+}
+
+impl Utf8Validator {
+    pub fn new() -> Self {
+        Utf8Validator { pending: Vec::new() }
+    }
+
+    pub fn feed(&mut self, data: &[u8]) -> RsResult<()> {
+        let mut data = data;
+        if !self.pending.is_empty() {
+            let held = self.pending.len();
+            let taken = data.len().min(4 - held);
+            self.pending.extend_from_slice(&data[..taken]);
+            let completed = match std::str::from_utf8(&self.pending) {
+                Ok(_) => taken,
+                Err(error) if error.valid_up_to() > 0 => error.valid_up_to() - held,
+                // still no complete code point, `data` was too short to finish it
+                Err(error) if error.error_len().is_none() => return Ok(()),
+                Err(_) => return Err(RsError::InvalidUtf8),
+            };
+            self.pending.clear();
+            data = &data[completed..];
+        }
+
+        match std::str::from_utf8(data) {
+            Ok(_) => Ok(()),
+            Err(error) if error.error_len().is_none() => {
+                self.pending.extend_from_slice(&data[error.valid_up_to()..]);
+                Ok(())
+            }
+            Err(_) => Err(RsError::InvalidUtf8),
+        }
+    }
+
+    // Called after the final fragment, fails if it ended partway through a code point.
+    pub fn finish(&mut self) -> RsResult<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        self.pending.clear();
+        Err(RsError::InvalidUtf8)
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+    }
+}
@@ -0,0 +1,132 @@
+use rusty_socket_core::{CloseCode, DataFrame, Message, MessageAssembler, OpCode, RsError};
+
+#[test]
+fn test_fragments_split_payload() {
+    let frames = DataFrame::fragments("Hello World", OpCode::Text, false, 4).expect("Failed to fragment");
+
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[0].get_opcode(), OpCode::Text);
+    assert_eq!(frames[1].get_opcode(), OpCode::ContinuationFrame);
+    assert_eq!(frames[2].get_opcode(), OpCode::ContinuationFrame);
+    assert!(!frames[0].is_final_fragment());
+    assert!(!frames[1].is_final_fragment());
+    assert!(frames[2].is_final_fragment());
+    assert_eq!(frames[2].payload, b"rld".to_vec());
+}
+
+#[test]
+fn test_fragments_never_split_control_frames() {
+    let frames = DataFrame::fragments("ping payload", OpCode::Ping, false, 4).expect("Failed to fragment");
+
+    assert_eq!(frames.len(), 1);
+    assert!(frames[0].is_final_fragment());
+}
+
+#[test]
+fn test_assembler_reassembles_fragments() {
+    let mut assembler = MessageAssembler::new();
+    let frames = DataFrame::fragments(vec![1u8; 10], OpCode::Binary, false, 3).expect("Failed to fragment");
+    let frame_count = frames.len();
+
+    for (i, frame) in frames.into_iter().enumerate() {
+        let result = assembler.push(frame).expect("Failed to assemble");
+        if i + 1 < frame_count {
+            assert!(result.is_none());
+            assert!(assembler.is_assembling());
+        } else {
+            assert_eq!(result, Some(Message::Binary(vec![1u8; 10])));
+        }
+    }
+    assert!(!assembler.is_assembling());
+}
+
+#[test]
+fn test_assembler_passes_interleaved_control_frames() {
+    let mut assembler = MessageAssembler::new();
+    let mut frames = DataFrame::fragments("Hello", OpCode::Text, false, 3).expect("Failed to fragment");
+    let last = frames.pop().unwrap();
+    let first = frames.pop().unwrap();
+    let ping = DataFrame::from_data("hb", OpCode::Ping, false).unwrap();
+
+    assert_eq!(assembler.push(first).unwrap(), None);
+    assert_eq!(assembler.push(ping).unwrap(), Some(Message::Ping(b"hb".to_vec())));
+    assert_eq!(assembler.push(last).unwrap(), Some(Message::Text("Hello".to_string())));
+}
+
+#[test]
+fn test_assembler_rejects_orphan_continuation() {
+    let mut assembler = MessageAssembler::new();
+    let frame = DataFrame::from_data("lost", OpCode::ContinuationFrame, false).unwrap();
+
+    assert!(matches!(assembler.push(frame), Err(RsError::UnexpectedContinuation)));
+}
+
+#[test]
+fn test_assembler_rejects_new_message_mid_fragment() {
+    let mut assembler = MessageAssembler::new();
+    let mut first = DataFrame::from_data("part", OpCode::Text, false).unwrap();
+    first.unset_final_fragment();
+    let second = DataFrame::from_data("other", OpCode::Text, false).unwrap();
+
+    assert_eq!(assembler.push(first).unwrap(), None);
+    assert!(matches!(assembler.push(second), Err(RsError::UnfinishedMessage)));
+}
+
+#[test]
+fn test_assembler_rejects_invalid_utf8_text() {
+    let mut assembler = MessageAssembler::new();
+    let frame = DataFrame::from_data([0xff, 0xfe], OpCode::Text, false).unwrap();
+
+    assert!(matches!(assembler.push(frame), Err(RsError::InvalidUtf8)));
+}
+
+#[test]
+fn test_assembler_validates_text_fragments() {
+    let text = "café €5";
+    let frames = DataFrame::fragments(text, OpCode::Text, false, 4).expect("Failed to fragment");
+    let mut assembler = MessageAssembler::new();
+    let mut received = None;
+    for frame in frames {
+        received = assembler.push(frame).unwrap();
+    }
+    assert_eq!(received, Some(Message::Text(text.to_string())));
+
+    // fails on the first fragment, before the message is complete
+    let mut first = DataFrame::from_data([0x61, 0xc0, 0xaf], OpCode::Text, false).unwrap();
+    first.unset_final_fragment();
+    let error = assembler.push(first).unwrap_err();
+    assert!(matches!(error, RsError::InvalidUtf8));
+    assert_eq!(error.close_code(), CloseCode::InvalidPayload);
+
+    // message ending partway through a code point
+    let mut assembler = MessageAssembler::new();
+    let mut first = DataFrame::from_data("ok", OpCode::Text, false).unwrap();
+    first.unset_final_fragment();
+    let last = DataFrame::from_data([0xe2, 0x82], OpCode::ContinuationFrame, false).unwrap();
+    assert_eq!(assembler.push(first).unwrap(), None);
+    assert!(matches!(assembler.push(last), Err(RsError::InvalidUtf8)));
+}
+
+#[test]
+fn test_assembler_limits_message_size() {
+    let mut assembler = MessageAssembler::new().with_max_message_size(8);
+    for frame in DataFrame::fragments("12345678", OpCode::Text, false, 3).unwrap() {
+        assembler.push(frame).unwrap();
+    }
+
+    // the limit covers all fragments together
+    let mut frames = DataFrame::fragments("123456789", OpCode::Binary, false, 3).unwrap().into_iter();
+    assert_eq!(assembler.push(frames.next().unwrap()).unwrap(), None);
+    assert_eq!(assembler.push(frames.next().unwrap()).unwrap(), None);
+    let error = assembler.push(frames.next().unwrap()).unwrap_err();
+    assert!(matches!(error, RsError::MessageTooLarge));
+    assert_eq!(error.close_code(), CloseCode::MessageTooBig);
+
+    let mut assembler = MessageAssembler::new().with_max_message_size(8);
+    let frame = DataFrame::from_data("123456789", OpCode::Text, false).unwrap();
+    assert!(matches!(assembler.push(frame), Err(RsError::MessageTooLarge)));
+
+    // control frames are bounded by the protocol instead
+    let ping = DataFrame::from_data([0; 100], OpCode::Ping, false).unwrap();
+    assert!(assembler.push(ping).is_ok());
+}
@@ -0,0 +1,178 @@
+#![cfg(feature = "tokio")]
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use rusty_socket_core::{
+    AsyncSocket, CloseCode, CloseFrame, ConnectionStatus, DataFrame, FrameDecoder, Message, MessageAssembler, OpCode,
+    Role, RsError,
+};
+use tokio::io::{duplex, AsyncRead, AsyncWrite, DuplexStream, ReadBuf};
+
+fn socket_pair() -> (AsyncSocket<DuplexStream>, AsyncSocket<DuplexStream>) {
+    let (client_stream, server_stream) = duplex(4096);
+
+    (
+        AsyncSocket::new(client_stream, Role::Client).fragment_size(Some(4)),
+        AsyncSocket::new(server_stream, Role::Server),
+    )
+}
+
+#[tokio::test]
+async fn test_async_fragmented_text_round_trip() {
+    let (client, mut server) = socket_pair();
+
+    client.send("Hello, async world").await.unwrap();
+    let received = server.next().await.unwrap().unwrap();
+    assert_eq!(received, Message::Text(String::from("Hello, async world")));
+}
+
+#[tokio::test]
+async fn test_async_ping_answered_with_pong() {
+    let (mut client, mut server) = socket_pair();
+
+    client.send(Message::Ping(b"are you there".to_vec())).await.unwrap();
+    let received = server.next().await.unwrap().unwrap();
+    assert_eq!(received, Message::Ping(b"are you there".to_vec()));
+
+    let answer = client.next().await.unwrap().unwrap();
+    assert_eq!(answer, Message::Pong(b"are you there".to_vec()));
+}
+
+#[tokio::test]
+async fn test_async_oversized_ping_is_refused() {
+    let (client, _server) = socket_pair();
+
+    let result = client.send(Message::Ping(vec![0; 126])).await;
+    assert!(matches!(result, Err(RsError::ControlFrameTooLarge)));
+    assert_eq!(client.status(), ConnectionStatus::Open);
+}
+
+#[tokio::test]
+async fn test_async_close_handshake() {
+    let (mut client, mut server) = socket_pair();
+
+    let server_task = tokio::spawn(async move {
+        let received = server.next().await.unwrap().unwrap();
+        let after_close = server.next().await;
+        (received, after_close.is_none(), server.status())
+    });
+
+    let peer_close = client.close(CloseFrame::new(CloseCode::GoingAway, "bye")).await.unwrap();
+    assert_eq!(peer_close, Some(CloseFrame::new(CloseCode::GoingAway, "")));
+    assert_eq!(client.status(), ConnectionStatus::Closed);
+
+    let (received, finished, status) = server_task.await.unwrap();
+    assert_eq!(received, Message::Close(Some(CloseFrame::new(CloseCode::GoingAway, "bye"))));
+    assert!(finished);
+    assert_eq!(status, ConnectionStatus::Closed);
+    assert!(client.send("too late").await.is_err());
+}
+
+#[tokio::test]
+async fn test_async_buffered_bytes_are_decoded() {
+    let (_, server_stream) = duplex(64);
+    let frame = DataFrame::from_data(b"leftover", OpCode::Binary, true).unwrap();
+    let mut server = AsyncSocket::new(server_stream, Role::Server).buffered(&Vec::from(frame));
+
+    let received = server.next().await.unwrap().unwrap();
+    assert_eq!(received, Message::Binary(b"leftover".to_vec()));
+}
+
+#[tokio::test]
+async fn test_async_protocol_error_fails_connection() {
+    let (client_stream, server_stream) = duplex(256);
+    let orphan = DataFrame::from_data(b"orphan", OpCode::ContinuationFrame, true).unwrap();
+    let mut server = AsyncSocket::new(server_stream, Role::Server).buffered(&Vec::from(orphan));
+    let mut client = AsyncSocket::new(client_stream, Role::Client);
+
+    assert!(server.next().await.unwrap().is_err());
+    assert!(server.next().await.is_none());
+    assert_eq!(server.status(), ConnectionStatus::Closed);
+
+    let received = client.next().await.unwrap().unwrap();
+    match received {
+        Message::Close(Some(close_frame)) => assert_eq!(close_frame.code, CloseCode::ProtocolError),
+        other => panic!("Expected close frame, got {}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_async_invalid_utf8_closes_with_1007() {
+    let (client_stream, server_stream) = duplex(256);
+    let text = DataFrame::from_data([0xff, 0xfe], OpCode::Text, true).unwrap();
+    let mut server = AsyncSocket::new(server_stream, Role::Server).buffered(&Vec::from(text));
+    let mut client = AsyncSocket::new(client_stream, Role::Client);
+
+    assert!(matches!(server.next().await.unwrap(), Err(RsError::InvalidUtf8)));
+
+    let received = client.next().await.unwrap().unwrap();
+    match received {
+        Message::Close(Some(close_frame)) => assert_eq!(close_frame.code, CloseCode::InvalidPayload),
+        other => panic!("Expected close frame, got {}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_async_oversized_message_closes_with_1009() {
+    let (client_stream, server_stream) = duplex(256);
+    let mut server = AsyncSocket::new(server_stream, Role::Server).max_message_size(4);
+    let mut client = AsyncSocket::new(client_stream, Role::Client);
+
+    client.send(Message::Text("small".to_string())).await.unwrap();
+    assert!(matches!(server.next().await.unwrap(), Err(RsError::MessageTooLarge)));
+
+    let received = client.next().await.unwrap().unwrap();
+    match received {
+        Message::Close(Some(close_frame)) => assert_eq!(close_frame.code, CloseCode::MessageTooBig),
+        other => panic!("Expected close frame, got {}", other),
+    }
+}
+
+// Never delivers anything and keeps every write it is handed.
+#[derive(Clone, Default)]
+struct Recorder {
+    writes: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl AsyncRead for Recorder {
+    fn poll_read(self: Pin<&mut Self>, _cx: &mut Context<'_>, _buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        Poll::Pending
+    }
+}
+
+impl AsyncWrite for Recorder {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        self.writes.lock().unwrap().push(buf.to_vec());
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn test_async_masked_send_streams_the_payload() {
+    let recorder = Recorder::default();
+    let client = AsyncSocket::new(recorder.clone(), Role::Client);
+    let message = Message::Binary((0..1 << 20).map(|i| (i % 251) as u8).collect());
+
+    client.send(message.clone()).await.unwrap();
+
+    // the payload is masked through a small buffer, never copied as a whole
+    let writes = recorder.writes.lock().unwrap();
+    assert!(writes.iter().all(|write| write.len() <= 4096));
+
+    let mut decoder = FrameDecoder::with_role(Role::Server);
+    let frames = decoder.feed(&writes.concat()).unwrap();
+    assert_eq!(frames.len(), 1);
+    let received = MessageAssembler::new().push(frames.into_iter().next().unwrap()).unwrap();
+    assert_eq!(received, Some(message));
+}
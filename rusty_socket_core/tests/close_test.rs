@@ -0,0 +1,46 @@
+use std::convert::TryFrom;
+
+use rusty_socket_core::{CloseCode, CloseFrame, OpCode, RsError};
+
+#[test]
+fn test_close_code_conversion() {
+    assert_eq!(CloseCode::try_from(1000).unwrap(), CloseCode::Normal);
+    assert_eq!(CloseCode::try_from(1001).unwrap(), CloseCode::GoingAway);
+    assert_eq!(CloseCode::try_from(1009).unwrap(), CloseCode::MessageTooBig);
+    assert_eq!(CloseCode::try_from(4321).unwrap(), CloseCode::Application(4321));
+    assert_eq!(u16::from(CloseCode::InternalError), 1011);
+    assert_eq!(u16::from(CloseCode::Application(4000)), 4000);
+}
+
+#[test]
+fn test_close_code_rejects_reserved() {
+    for code in [0, 999, 1004, 1005, 1006, 1015, 2000, 5000] {
+        assert!(matches!(CloseCode::try_from(code), Err(RsError::InvalidCloseCode)));
+    }
+}
+
+#[test]
+fn test_close_frame_round_trip() {
+    let close_frame = CloseFrame::new(CloseCode::GoingAway, "server restart");
+    let frame = close_frame.to_dataframe(true).expect("Failed to create frame");
+
+    assert_eq!(frame.get_opcode(), OpCode::ConnectionClose);
+
+    let parsed = CloseFrame::from_payload(&close_frame.to_payload()).unwrap();
+    assert_eq!(parsed, Some(close_frame));
+}
+
+#[test]
+fn test_close_frame_empty_payload() {
+    assert_eq!(CloseFrame::from_payload(&[]).unwrap(), None);
+    assert!(CloseFrame::from_payload(&[0x03]).is_err());
+}
+
+#[test]
+fn test_close_frame_truncates_long_reason() {
+    let reason = "é".repeat(100);
+    let close_frame = CloseFrame::new(CloseCode::Normal, &reason);
+
+    assert!(close_frame.reason.len() <= 123);
+    assert!(close_frame.to_payload().len() <= 125);
+}
@@ -18,6 +18,32 @@ fn test_fin_bit_modification() {
     assert_eq!(false, frame.is_final_fragment());
 }
 
+#[test]
+fn test_rsv_bit_modification() {
+    let mut frame = DataFrame {
+        fin_rscv_opcode: 0b10000001,
+        mask_payload_length: 0,
+        extended_payload_length: None,
+        masking_key: None,
+        payload: Vec::new(),
+    };
+    assert_eq!(frame.get_rsv_bits(), 0);
+
+    frame.set_rsv1();
+    frame.set_rsv3();
+    assert!(frame.is_rsv1());
+    assert!(!frame.is_rsv2());
+    assert!(frame.is_rsv3());
+    assert_eq!(frame.get_rsv_bits(), 0b01010000);
+
+    frame.unset_rsv1();
+    frame.set_rsv2();
+    frame.unset_rsv3();
+    assert_eq!(frame.get_rsv_bits(), 0b00100000);
+    assert_eq!(frame.fin_rscv_opcode, 0b10100001);
+    assert_eq!(frame.get_opcode(), OpCode::Text);
+}
+
 #[test]
 fn test_mask_bit_modification() {
     let mut frame = DataFrame {
@@ -114,3 +140,41 @@ fn test_invalid_frame_insufficient_data() {
     assert!(result.is_err());
     assert_eq!(result.unwrap_err().to_string(), "Insufficient Data");
 }
+
+#[test]
+fn test_reserved_opcodes_rejected() {
+    for opcode in (0x3..=0x7).chain(0xb..=0xf) {
+        let raw_data: &[u8] = &[0x80 | opcode, 0x00];
+        assert!(matches!(DataFrame::try_from(raw_data), Err(RsError::InvalidOpCode)));
+    }
+}
+
+#[test]
+fn test_invalid_control_frames_rejected() {
+    // ping without the FIN bit
+    let raw_data: &[u8] = &[0x09, 0x00];
+    assert!(matches!(DataFrame::try_from(raw_data), Err(RsError::FragmentedControlFrame)));
+
+    // close with a 126 byte payload, rejected before the payload arrives
+    let raw_data: &[u8] = &[0x88, 0x7e, 0x00, 0x7e];
+    assert!(matches!(DataFrame::try_from(raw_data), Err(RsError::ControlFrameTooLarge)));
+
+    let mut raw_data = vec![0x8a, 0x7d];
+    raw_data.extend_from_slice(&[0; 125]);
+    assert!(DataFrame::try_from(raw_data.as_slice()).is_ok());
+}
+
+#[test]
+fn test_payload_length_encoding_checked() {
+    let raw_data: &[u8] = &[0x82, 0x7e, 0x00, 0x7d];
+    assert!(matches!(DataFrame::try_from(raw_data), Err(RsError::NonMinimalLength)));
+
+    let raw_data: &[u8] = &[0x82, 0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff];
+    assert!(matches!(DataFrame::try_from(raw_data), Err(RsError::NonMinimalLength)));
+
+    let raw_data: &[u8] = &[0x82, 0x7f, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+    assert!(matches!(DataFrame::try_from(raw_data), Err(RsError::InvalidPayloadLength)));
+
+    let raw_data: &[u8] = &[0x82, 0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00];
+    assert!(matches!(DataFrame::try_from(raw_data), Err(RsError::IncompleteData)));
+}
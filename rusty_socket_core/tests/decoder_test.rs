@@ -0,0 +1,119 @@
+use rusty_socket_core::{CloseCode, DataFrame, FrameDecoder, OpCode, Role, RsError, RSV1, RSV2};
+
+#[test]
+fn test_parse_reports_consumed_bytes() {
+    let raw_data: &[u8] = &[0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x81];
+
+    let (frame, consumed) = DataFrame::parse(raw_data).expect("Failed to parse");
+    assert_eq!(consumed, 7);
+    assert_eq!(frame.payload, b"Hello".to_vec());
+}
+
+#[test]
+fn test_decoder_partial_frame() {
+    let mut decoder = FrameDecoder::new();
+
+    let frames = decoder.feed(&[0x81, 0x05, 0x48]).expect("Failed to decode");
+    assert!(frames.is_empty());
+    assert_eq!(decoder.buffered(), 3);
+
+    let frames = decoder.feed(&[0x65, 0x6c, 0x6c, 0x6f]).expect("Failed to decode");
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].payload, b"Hello".to_vec());
+    assert_eq!(decoder.buffered(), 0);
+}
+
+#[test]
+fn test_decoder_coalesced_frames() {
+    let mut decoder = FrameDecoder::new();
+    let raw_data: &[u8] = &[
+        0x81, 0x02, 0x48, 0x69, // "Hi"
+        0x82, 0x01, 0x07, // binary 7
+        0x81, 0x05, 0x48, // start of "Hello"
+    ];
+
+    let frames = decoder.feed(raw_data).expect("Failed to decode");
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].payload, b"Hi".to_vec());
+    assert_eq!(frames[1].get_opcode(), OpCode::Binary);
+    assert_eq!(frames[1].payload, vec![7]);
+    assert_eq!(decoder.buffered(), 3);
+}
+
+#[test]
+fn test_decoder_large_masked_payload_in_chunks() {
+    let data: Vec<u8> = (0..70000).map(|i| (i % 251) as u8).collect();
+    let frame = DataFrame::from_data(&data, OpCode::Binary, true).expect("Failed to create");
+    let frame_bytes = Vec::from(frame);
+
+    let mut decoder = FrameDecoder::new();
+    let mut frames = Vec::new();
+    for chunk in frame_bytes.chunks(512) {
+        frames.extend(decoder.feed(chunk).expect("Failed to decode"));
+    }
+
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].payload, data);
+}
+
+#[test]
+fn test_decoder_propagates_errors() {
+    let mut decoder = FrameDecoder::new();
+    let result = decoder.feed(&[0x8f, 0x00]);
+
+    assert!(matches!(result, Err(RsError::InvalidOpCode)));
+}
+
+#[test]
+fn test_decoder_rejects_unnegotiated_rsv_bits() {
+    let mut decoder = FrameDecoder::new();
+    assert!(matches!(decoder.feed(&[0xc1, 0x00]), Err(RsError::ReservedBitsSet)));
+
+    let mut decoder = FrameDecoder::new();
+    decoder.set_reserved_bits(RSV1);
+    let frames = decoder.feed(&[0xc1, 0x00]).expect("Failed to decode");
+    assert!(frames[0].is_rsv1());
+    assert!(matches!(decoder.feed(&[0xa1, 0x00]), Err(RsError::ReservedBitsSet)));
+
+    decoder.set_reserved_bits(RSV1 | RSV2);
+    assert_eq!(decoder.feed(&[0xe1, 0x00]).expect("Failed to decode").len(), 1);
+}
+
+#[test]
+fn test_decoder_checks_masking_for_role() {
+    let masked = Vec::from(DataFrame::from_data("Hello", OpCode::Text, true).unwrap());
+    let unmasked = Vec::from(DataFrame::from_data("Hello", OpCode::Text, false).unwrap());
+
+    let mut server = FrameDecoder::with_role(Role::Server);
+    assert_eq!(server.feed(&masked).expect("Failed to decode")[0].payload, b"Hello");
+    let error = server.feed(&unmasked).unwrap_err();
+    assert!(matches!(error, RsError::UnmaskedFrame));
+    assert_eq!(error.close_code(), CloseCode::ProtocolError);
+
+    let mut client = FrameDecoder::with_role(Role::Client);
+    assert_eq!(client.feed(&unmasked).expect("Failed to decode")[0].payload, b"Hello");
+    assert!(matches!(client.feed(&masked), Err(RsError::MaskedFrame)));
+
+    // without a role both are accepted
+    let mut decoder = FrameDecoder::new();
+    assert_eq!(decoder.feed(&[masked, unmasked].concat()).expect("Failed to decode").len(), 2);
+}
+
+#[test]
+fn test_decoder_rejects_oversized_frame_from_header() {
+    let mut decoder = FrameDecoder::new();
+    decoder.set_max_frame_size(1024);
+
+    // a header announcing 2^62 bytes fails before any payload is buffered
+    let header = [0x82, 0xff, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04];
+    let error = decoder.feed(&header).unwrap_err();
+    assert!(matches!(error, RsError::FrameTooLarge));
+    assert_eq!(error.close_code(), CloseCode::MessageTooBig);
+
+    let mut decoder = FrameDecoder::new();
+    decoder.set_max_frame_size(5);
+    let frame = Vec::from(DataFrame::from_data("Hello", OpCode::Text, false).unwrap());
+    assert_eq!(decoder.feed(&frame).expect("Failed to decode").len(), 1);
+    let frame = Vec::from(DataFrame::from_data("Hello!", OpCode::Text, false).unwrap());
+    assert!(matches!(decoder.feed(&frame), Err(RsError::FrameTooLarge)));
+}
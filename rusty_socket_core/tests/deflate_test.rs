@@ -0,0 +1,237 @@
+use std::sync::{Arc, Mutex};
+
+use rusty_socket_core::{
+    DataFrame, DeflateConfig, Extension, Extensions, Message, MessageAssembler, OpCode, PerMessageDeflate, Role,
+    RsError,
+};
+
+#[test]
+fn test_deflate_default_negotiation() {
+    let client = DeflateConfig::default();
+    let server = DeflateConfig::default();
+
+    let offer = client.offer_header();
+    assert_eq!(offer, "permessage-deflate; client_max_window_bits");
+
+    let agreed = server.negotiate(&offer).expect("Offer should be accepted");
+    assert_eq!(agreed, DeflateConfig::default());
+    assert_eq!(agreed.response_header(), "permessage-deflate");
+    assert_eq!(client.accept_response(&agreed.response_header()).unwrap(), agreed);
+}
+
+#[test]
+fn test_deflate_negotiates_parameters() {
+    let server = DeflateConfig {
+        client_no_context_takeover: true,
+        client_max_window_bits: 12,
+        ..DeflateConfig::default()
+    };
+    let offer = "permessage-deflate; server_no_context_takeover; server_max_window_bits=10; client_max_window_bits";
+
+    let agreed = server.negotiate(offer).unwrap();
+    assert!(agreed.server_no_context_takeover);
+    assert!(agreed.client_no_context_takeover);
+    assert_eq!(agreed.server_max_window_bits, 10);
+    assert_eq!(agreed.client_max_window_bits, 12);
+    assert_eq!(
+        agreed.response_header(),
+        "permessage-deflate; server_no_context_takeover; client_no_context_takeover; server_max_window_bits=10; client_max_window_bits=12"
+    );
+}
+
+#[test]
+fn test_deflate_skips_unacceptable_offers() {
+    let server = DeflateConfig::default();
+
+    assert_eq!(server.negotiate("x-webkit-deflate-frame"), None);
+    assert_eq!(server.negotiate("permessage-deflate; unknown_param"), None);
+    assert_eq!(server.negotiate("permessage-deflate; server_no_context_takeover; server_no_context_takeover"), None);
+    assert_eq!(server.negotiate("permessage-deflate; server_max_window_bits=16"), None);
+
+    // a window of 8 bits cannot be honoured, the fallback offer is taken instead
+    let agreed = server
+        .negotiate("permessage-deflate; server_max_window_bits=8, permessage-deflate; server_max_window_bits=\"11\"")
+        .unwrap();
+    assert_eq!(agreed.server_max_window_bits, 11);
+    // without client_max_window_bits in the offer the client keeps the full window
+    assert_eq!(agreed.client_max_window_bits, 15);
+}
+
+#[test]
+fn test_deflate_skips_client_window_of_8_bits() {
+    let server = DeflateConfig::default();
+
+    assert_eq!(server.negotiate("permessage-deflate; client_max_window_bits=8"), None);
+    let agreed = server
+        .negotiate("permessage-deflate; client_max_window_bits=8, permessage-deflate; client_max_window_bits=9")
+        .unwrap();
+    assert_eq!(agreed.client_max_window_bits, 9);
+
+    // the client refuses an answer of 8 the same way
+    let response = "permessage-deflate; client_max_window_bits=8";
+    assert!(DeflateConfig::default().accept_response(response).is_err());
+}
+
+#[test]
+fn test_deflate_client_rejects_bad_response() {
+    let client = DeflateConfig {
+        server_max_window_bits: 10,
+        ..DeflateConfig::default()
+    };
+
+    assert!(client.accept_response("permessage-deflate").is_err());
+    assert!(client.accept_response("permessage-deflate; server_max_window_bits=12").is_err());
+    assert!(client.accept_response("permessage-deflate; server_max_window_bits=10; foo").is_err());
+    assert!(client.accept_response("permessage-deflate; server_max_window_bits=10; client_max_window_bits").is_err());
+
+    let agreed = client.accept_response("permessage-deflate; server_max_window_bits=9").unwrap();
+    assert_eq!(agreed.server_max_window_bits, 9);
+}
+
+#[test]
+fn test_deflate_decodes_rfc_example() {
+    // RFC 7692 section 7.2.3.2, the second "Hello" refers back to the first
+    let mut decoder = DeflateConfig::default().decoder(Role::Client);
+
+    assert_eq!(decoder.decompress(&[0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00]).unwrap(), b"Hello");
+    assert_eq!(decoder.decompress(&[0xf2, 0x00, 0x11, 0x00, 0x00]).unwrap(), b"Hello");
+}
+
+#[test]
+fn test_deflate_round_trip_with_and_without_context_takeover() {
+    let json = r#"{"user":"alice","action":"update","fields":{"status":"online","status_text":"online"}}"#;
+
+    for no_context_takeover in [false, true] {
+        let config = DeflateConfig {
+            server_no_context_takeover: no_context_takeover,
+            ..DeflateConfig::default()
+        };
+        let mut encoder = config.encoder(Role::Server);
+        let mut decoder = config.decoder(Role::Client);
+
+        let first = encoder.compress(json.as_bytes()).unwrap();
+        let second = encoder.compress(json.as_bytes()).unwrap();
+        assert!(first.len() < json.len());
+        if no_context_takeover {
+            assert_eq!(first, second);
+        } else {
+            assert!(second.len() < first.len());
+        }
+
+        assert_eq!(decoder.decompress(&first).unwrap(), json.as_bytes());
+        assert_eq!(decoder.decompress(&second).unwrap(), json.as_bytes());
+    }
+}
+
+fn negotiated_pair(config: DeflateConfig) -> (Extensions, Extensions) {
+    let offer = PerMessageDeflate::new(config, Role::Client).offer();
+    let server = Extensions::negotiate(vec![Box::new(PerMessageDeflate::new(config, Role::Server))], &offer);
+    let client = Extensions::accept(
+        vec![Box::new(PerMessageDeflate::new(config, Role::Client))],
+        &server.header().unwrap(),
+    )
+    .unwrap();
+
+    (client, server)
+}
+
+#[test]
+fn test_assembler_inflates_compressed_fragments() {
+    let (mut client, server) = negotiated_pair(DeflateConfig::default());
+    let mut assembler = MessageAssembler::new().with_extensions(Arc::new(Mutex::new(server)));
+    let message = Message::Text("compress me ".repeat(50));
+
+    let frames = client.to_frames(&message, true, 8).unwrap();
+    assert!(frames.len() > 1);
+    assert!(frames[0].is_rsv1());
+    assert!(frames[0].is_masked());
+    assert!(!frames[1].is_rsv1());
+
+    let mut received = None;
+    for frame in frames {
+        let frame = DataFrame::try_from(&Vec::from(frame)[..]).unwrap();
+        received = assembler.push(frame).unwrap();
+    }
+    assert_eq!(received, Some(message));
+
+    // uncompressed messages are still allowed
+    let plain = DataFrame::from_data("plain", OpCode::Text, false).unwrap();
+    assert_eq!(assembler.push(plain).unwrap(), Some(Message::Text(String::from("plain"))));
+}
+
+#[test]
+fn test_deflate_keeps_context_across_messages() {
+    let (mut client, server) = negotiated_pair(DeflateConfig::default());
+    let mut assembler = MessageAssembler::new().with_extensions(Arc::new(Mutex::new(server)));
+    let message = Message::Binary((0..2000).map(|i| (i % 251) as u8).collect());
+
+    let mut sizes = Vec::new();
+    for _ in 0..2 {
+        let frames = client.to_frames(&message, true, 100).unwrap();
+        sizes.push(frames.iter().map(|frame| frame.payload.len()).sum::<usize>());
+
+        let mut received = None;
+        for frame in frames {
+            received = assembler.push(DataFrame::try_from(&Vec::from(frame)[..]).unwrap()).unwrap();
+        }
+        assert_eq!(received, Some(message.clone()));
+    }
+    // the second copy refers back to the first
+    assert!(sizes[1] < sizes[0]);
+}
+
+#[test]
+fn test_assembler_stops_inflating_past_message_limit() {
+    let (mut client, server) = negotiated_pair(DeflateConfig::default());
+    let mut assembler = MessageAssembler::new()
+        .with_extensions(Arc::new(Mutex::new(server)))
+        .with_max_message_size(64 * 1024);
+
+    // a megabyte of zeros compresses to about a kilobyte
+    let message = Message::Binary(vec![0; 1 << 20]);
+    let frames = client.to_frames(&message, true, 0).unwrap();
+    assert_eq!(frames.len(), 1);
+    assert!(frames[0].payload.len() < 64 * 1024);
+
+    let frame = DataFrame::try_from(&Vec::from(frames.into_iter().next().unwrap())[..]).unwrap();
+    assert!(matches!(assembler.push(frame), Err(RsError::MessageTooLarge)));
+
+    let mut decoder = DeflateConfig::default().decoder(Role::Server);
+    let compressed = DeflateConfig::default().encoder(Role::Client).compress(&[0; 1 << 20]).unwrap();
+    assert!(matches!(
+        decoder.decompress_frame_limited(&compressed, true, 1000),
+        Err(RsError::MessageTooLarge)
+    ));
+}
+
+#[test]
+fn test_assembler_rejects_unnegotiated_rsv1() {
+    let mut frame = DataFrame::from_data("data", OpCode::Text, false).unwrap();
+    frame.set_rsv1();
+    assert!(matches!(MessageAssembler::new().push(frame), Err(RsError::ReservedBitsSet)));
+
+    let (_, server) = negotiated_pair(DeflateConfig::default());
+    let mut assembler = MessageAssembler::new().with_extensions(Arc::new(Mutex::new(server)));
+    let mut ping = DataFrame::from_data("ping", OpCode::Ping, false).unwrap();
+    ping.set_rsv1();
+    assert!(matches!(assembler.push(ping), Err(RsError::ReservedBitsSet)));
+
+    let mut first = DataFrame::from_data("first", OpCode::Text, false).unwrap();
+    first.unset_final_fragment();
+    assert_eq!(assembler.push(first).unwrap(), None);
+    let mut continuation = DataFrame::from_data("second", OpCode::ContinuationFrame, false).unwrap();
+    continuation.set_rsv1();
+    assert!(matches!(assembler.push(continuation), Err(RsError::ReservedBitsSet)));
+}
+
+#[test]
+fn test_deflate_config_from_response_header() {
+    let server = DeflateConfig {
+        server_no_context_takeover: true,
+        server_max_window_bits: 10,
+        ..DeflateConfig::default()
+    };
+    let agreed = server.negotiate("permessage-deflate; client_max_window_bits").unwrap();
+    assert_eq!(DeflateConfig::from_response_header(&agreed.response_header()), Some(agreed));
+    assert_eq!(DeflateConfig::from_response_header("x-invert"), None);
+}
@@ -0,0 +1,136 @@
+use std::sync::{Arc, Mutex};
+
+use rusty_socket_core::{
+    DataFrame, DeflateConfig, Extension, Extensions, Message, MessageAssembler, PerMessageDeflate, Role,
+    RsError, RsResult, RSV1, RSV2,
+};
+
+// Flips every payload byte and marks the first frame with RSV2.
+struct Invert;
+
+impl Extension for Invert {
+    fn name(&self) -> &str {
+        "x-invert"
+    }
+
+    fn rsv_bits(&self) -> u8 {
+        RSV2
+    }
+
+    fn offer(&self) -> String {
+        String::from("x-invert")
+    }
+
+    fn negotiate(&mut self, offer: &str) -> Option<String> {
+        (offer == "x-invert").then(|| String::from("x-invert"))
+    }
+
+    fn accept(&mut self, response: &str) -> RsResult<()> {
+        if response != "x-invert" {
+            return Err(RsError::ProtocolError);
+        }
+        Ok(())
+    }
+
+    fn encode(&mut self, mut frames: Vec<DataFrame>) -> RsResult<Vec<DataFrame>> {
+        for frame in frames.iter_mut() {
+            let inverted = frame.payload.iter().map(|byte| !byte).collect();
+            frame.set_payload(inverted);
+        }
+        if let Some(first_frame) = frames.first_mut() {
+            first_frame.set_rsv2();
+        }
+        Ok(frames)
+    }
+
+    fn decode(&mut self, mut frame: DataFrame) -> RsResult<DataFrame> {
+        let inverted = frame.payload.iter().map(|byte| !byte).collect();
+        frame.set_payload(inverted);
+        frame.unset_rsv2();
+        Ok(frame)
+    }
+}
+
+fn invert() -> Box<dyn Extension> {
+    Box::new(Invert)
+}
+
+#[test]
+fn test_extensions_negotiate_in_server_order() {
+    let offers = "x-unknown, x-invert; foo, x-invert, permessage-deflate";
+    let candidates: Vec<Box<dyn Extension>> = vec![
+        Box::new(PerMessageDeflate::new(DeflateConfig::default(), Role::Server)),
+        invert(),
+    ];
+
+    let extensions = Extensions::negotiate(candidates, offers);
+    assert_eq!(extensions.names(), vec!["permessage-deflate", "x-invert"]);
+    assert_eq!(extensions.rsv_bits(), RSV1 | RSV2);
+    assert_eq!(extensions.header().unwrap(), "permessage-deflate, x-invert");
+
+    let nothing = Extensions::negotiate(vec![invert()], "permessage-deflate");
+    assert!(nothing.is_empty());
+    assert_eq!(nothing.header(), None);
+}
+
+#[test]
+fn test_extensions_skip_clashing_rsv_bits() {
+    let candidates: Vec<Box<dyn Extension>> = vec![
+        Box::new(PerMessageDeflate::new(DeflateConfig::default(), Role::Server)),
+        Box::new(PerMessageDeflate::new(DeflateConfig::default(), Role::Server)),
+    ];
+
+    let extensions = Extensions::negotiate(candidates, "permessage-deflate, permessage-deflate");
+    assert_eq!(extensions.names(), vec!["permessage-deflate"]);
+}
+
+#[test]
+fn test_client_accepts_only_offered_extensions() {
+    let candidates = || -> Vec<Box<dyn Extension>> {
+        vec![Box::new(PerMessageDeflate::new(DeflateConfig::default(), Role::Client)), invert()]
+    };
+    assert_eq!(
+        Extensions::offer_header(&candidates()).unwrap(),
+        "permessage-deflate; client_max_window_bits, x-invert"
+    );
+    assert_eq!(Extensions::offer_header(&[]), None);
+
+    let extensions = Extensions::accept(candidates(), "x-invert, permessage-deflate").unwrap();
+    assert_eq!(extensions.names(), vec!["x-invert", "permessage-deflate"]);
+
+    assert!(Extensions::accept(candidates(), "x-unknown").is_err());
+    assert!(Extensions::accept(candidates(), "x-invert, x-invert").is_err());
+    assert!(Extensions::accept(candidates(), "x-invert; foo").is_err());
+}
+
+#[test]
+fn test_extensions_round_trip_through_assembler() {
+    let mut client = Extensions::accept(vec![invert()], "x-invert").unwrap();
+    let server = Extensions::negotiate(vec![invert()], "x-invert");
+    let mut assembler = MessageAssembler::new().with_extensions(Arc::new(Mutex::new(server)));
+
+    let message = Message::Binary(vec![1, 2, 3, 4, 5, 6]);
+    let frames = client.to_frames(&message, true, 4).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert!(frames[0].is_rsv2());
+    assert!(!frames[1].is_rsv2());
+
+    // the wire carries the inverted payload, masked after encoding
+    let wire: Vec<DataFrame> = frames
+        .into_iter()
+        .map(|frame| DataFrame::parse(&Vec::from(frame)).unwrap().0)
+        .collect();
+    assert_eq!(wire[0].payload, vec![!1, !2, !3, !4]);
+
+    let mut received = None;
+    for frame in wire {
+        received = assembler.push(frame).unwrap();
+    }
+    assert_eq!(received, Some(message));
+
+    // control messages bypass extensions
+    let ping = client.to_frames(&Message::Ping(vec![9]), true, 0).unwrap();
+    assert_eq!(ping[0].get_rsv_bits(), 0);
+    let (ping, _) = DataFrame::parse(&Vec::from(ping.into_iter().next().unwrap())).unwrap();
+    assert_eq!(ping.payload, vec![9]);
+}
@@ -0,0 +1,96 @@
+use std::io::{self, ErrorKind, Write};
+
+use rusty_socket_core::{CloseCode, CloseFrame, DataFrame, FrameDecoder, FrameHeader, Message, OpCode, Role, RSV1};
+
+// Accepts at most a few bytes per call, like a socket with a full send buffer.
+struct Trickle {
+    written: Vec<u8>,
+}
+
+impl Write for Trickle {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let size = buf.len().min(3);
+        self.written.extend_from_slice(&buf[..size]);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_header_matches_dataframe_encoding() {
+    for length in [0, 125, 126, 65535, 65536] {
+        let payload = vec![7u8; length];
+        let frame = DataFrame::from_data(&payload, OpCode::Binary, false).unwrap();
+        let header = FrameHeader::new(OpCode::Binary, length);
+        assert_eq!(header, frame.header());
+
+        let (bytes, size) = header.encode();
+        assert_eq!(size, header.size());
+        let mut encoded = Vec::new();
+        header.write_vectored(&payload, &mut encoded).unwrap();
+        assert_eq!(&encoded[..size], &bytes[..size]);
+        assert_eq!(encoded, Vec::from(frame));
+    }
+
+    let mut header = FrameHeader::new(OpCode::Text, 3);
+    header.fin = false;
+    header.rsv_bits = RSV1;
+    assert_eq!(header.encode().0[..2], [0x41, 0x03]);
+}
+
+#[test]
+fn test_masked_payload_streamed_in_chunks() {
+    let payload: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+    let mut header = FrameHeader::new(OpCode::Binary, payload.len());
+    header.mask();
+
+    let mut trickle = Trickle { written: Vec::new() };
+    header.encode_into(&payload, &mut trickle).unwrap();
+    assert_eq!(trickle.written.len(), header.size() + payload.len());
+
+    let mut decoder = FrameDecoder::with_role(Role::Server);
+    let frames = decoder.feed(&trickle.written).expect("Failed to decode");
+    assert_eq!(frames[0].payload, payload);
+}
+
+#[test]
+fn test_vectored_write_survives_partial_writes() {
+    let header = FrameHeader::new(OpCode::Text, 5);
+    let mut trickle = Trickle { written: Vec::new() };
+    header.write_vectored(b"Hello", &mut trickle).unwrap();
+    assert_eq!(trickle.written, vec![0x81, 0x05, b'H', b'e', b'l', b'l', b'o']);
+
+    let error = header.write_vectored(b"Hi", &mut Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_message_encode_into_matches_frames() {
+    let messages = [
+        Message::Binary(vec![1u8; 300]),
+        Message::Text(String::new()),
+        Message::Ping(vec![1, 2, 3, 4, 5]),
+        Message::Close(Some(CloseFrame::new(CloseCode::GoingAway, "bye"))),
+        Message::Close(None),
+    ];
+    for message in messages {
+        let mut encoded = Vec::new();
+        message.encode_into(&mut encoded, false, 128).unwrap();
+        let frames: Vec<u8> = message.to_frames(false, 128).unwrap().into_iter().flat_map(Vec::from).collect();
+        assert_eq!(encoded, frames);
+    }
+
+    // masked fragments decode back to the message
+    let message = Message::Text("fragmented text".to_string());
+    let mut encoded = Vec::new();
+    message.encode_into(&mut encoded, true, 4).unwrap();
+    let frames = FrameDecoder::with_role(Role::Server).feed(&encoded).expect("Failed to decode");
+    assert_eq!(frames.len(), 4);
+    assert!(frames[..3].iter().all(|frame| !frame.is_final_fragment()));
+    assert_eq!(frames[1].get_opcode(), OpCode::ContinuationFrame);
+    let payload: Vec<u8> = frames.into_iter().flat_map(|frame| frame.payload).collect();
+    assert_eq!(payload, b"fragmented text");
+}
@@ -0,0 +1,35 @@
+use std::time::{Duration, Instant};
+
+use rusty_socket_core::{Heartbeat, HeartbeatAction};
+
+#[test]
+fn test_heartbeat_sends_ping_after_interval() {
+    let mut heartbeat = Heartbeat::new(Duration::from_secs(30), Duration::from_secs(10));
+    let start = Instant::now();
+
+    assert_eq!(heartbeat.poll(start), HeartbeatAction::Idle);
+    assert_eq!(heartbeat.poll(start + Duration::from_secs(31)), HeartbeatAction::SendPing);
+    assert!(heartbeat.is_awaiting_pong());
+    assert_eq!(heartbeat.poll(start + Duration::from_secs(32)), HeartbeatAction::Idle);
+}
+
+#[test]
+fn test_heartbeat_detects_dead_peer() {
+    let mut heartbeat = Heartbeat::new(Duration::from_secs(30), Duration::from_secs(10));
+    let start = Instant::now();
+
+    assert_eq!(heartbeat.poll(start + Duration::from_secs(30)), HeartbeatAction::SendPing);
+    assert_eq!(heartbeat.poll(start + Duration::from_secs(41)), HeartbeatAction::PeerDead);
+}
+
+#[test]
+fn test_heartbeat_pong_resets_deadline() {
+    let mut heartbeat = Heartbeat::new(Duration::from_secs(30), Duration::from_secs(10));
+    let start = Instant::now();
+
+    assert_eq!(heartbeat.poll(start + Duration::from_secs(30)), HeartbeatAction::SendPing);
+    heartbeat.pong_received();
+    assert!(!heartbeat.is_awaiting_pong());
+    assert_eq!(heartbeat.poll(start + Duration::from_secs(41)), HeartbeatAction::Idle);
+    assert_eq!(heartbeat.remaining(start + Duration::from_secs(41)), Duration::from_secs(19));
+}
@@ -0,0 +1,60 @@
+use rusty_socket_core::{CloseCode, CloseFrame, Message, OpCode, RsError};
+
+#[test]
+fn test_message_from_payload() {
+    assert_eq!(
+        Message::from_payload(OpCode::Text, b"Hello".to_vec()).unwrap(),
+        Message::Text("Hello".to_string())
+    );
+    assert_eq!(
+        Message::from_payload(OpCode::Binary, vec![0xff, 0x00]).unwrap(),
+        Message::Binary(vec![0xff, 0x00])
+    );
+    assert_eq!(
+        Message::from_payload(OpCode::ConnectionClose, vec![0x03, 0xe8]).unwrap(),
+        Message::Close(Some(CloseFrame::new(CloseCode::Normal, "")))
+    );
+    assert!(matches!(
+        Message::from_payload(OpCode::Text, vec![0xc3]),
+        Err(RsError::InvalidUtf8)
+    ));
+}
+
+#[test]
+fn test_message_conversions() {
+    assert_eq!(Message::from("hi"), Message::Text("hi".to_string()));
+    assert_eq!(Message::from(vec![1, 2, 3]), Message::Binary(vec![1, 2, 3]));
+    assert_eq!(Message::from(vec![1, 2, 3]).opcode(), OpCode::Binary);
+    assert!(Message::Ping(Vec::new()).is_control());
+    assert!(!Message::Text(String::new()).is_control());
+}
+
+#[test]
+fn test_message_to_frames() {
+    let message = Message::Binary(vec![7u8; 10]);
+    let frames = message.to_frames(true, 4).expect("Failed to encode");
+
+    assert_eq!(frames.len(), 3);
+    assert_eq!(frames[0].get_opcode(), OpCode::Binary);
+    assert!(frames.iter().all(|frame| frame.is_masked()));
+
+    let close = Message::Close(Some(CloseFrame::new(CloseCode::GoingAway, "bye")));
+    let frames = close.to_frames(false, 1).expect("Failed to encode");
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].payload, vec![0x03, 0xe9, b'b', b'y', b'e']);
+}
+
+#[test]
+fn test_oversized_control_messages_are_not_encoded() {
+    let ping = Message::Ping(vec![0; 126]);
+    let mut encoded = Vec::new();
+    assert!(matches!(ping.encode_into(&mut encoded, true, 0), Err(RsError::ControlFrameTooLarge)));
+    assert!(encoded.is_empty());
+    assert!(ping.to_frames(false, 0).is_none());
+    assert!(Message::Pong(vec![0; 125]).encode_into(&mut encoded, false, 0).is_ok());
+
+    let mut close = CloseFrame::new(CloseCode::Normal, "");
+    close.reason = "x".repeat(124);
+    let close = Message::Close(Some(close));
+    assert!(matches!(close.encode_into(&mut Vec::new(), false, 0), Err(RsError::ControlFrameTooLarge)));
+}
@@ -0,0 +1,92 @@
+#![cfg(feature = "tls")]
+
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use rusty_socket_core::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use rusty_socket_core::rustls::{self, RootCertStore};
+use rusty_socket_core::{TlsStream, Transport};
+
+fn tls_configs() -> (Arc<rustls::ServerConfig>, Arc<rustls::ClientConfig>) {
+    let certified = rcgen::generate_simple_self_signed(vec![String::from("localhost")]).unwrap();
+    let cert: CertificateDer<'static> = certified.cert.der().clone();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let server_config = rustls::ServerConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![cert.clone()], key)
+        .unwrap();
+
+    let mut roots = RootCertStore::empty();
+    roots.add(cert).unwrap();
+    let client_config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    (Arc::new(server_config), Arc::new(client_config))
+}
+
+fn tls_pair(server_name: &str) -> (std::io::Result<TlsStream>, thread::JoinHandle<std::io::Result<TlsStream>>) {
+    let (server_config, client_config) = tls_configs();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (socket, _) = listener.accept()?;
+        TlsStream::accept(server_config, socket)
+    });
+    let client = TlsStream::connect(client_config, server_name, TcpStream::connect(address).unwrap());
+
+    (client, server)
+}
+
+#[test]
+fn test_tls_stream_round_trip() {
+    let (client, server) = tls_pair("localhost");
+    let mut client = client.expect("Client handshake failed");
+    let mut server = server.join().unwrap().expect("Server handshake failed");
+
+    client.write_all(b"hello over tls").unwrap();
+    client.flush().unwrap();
+    let mut buffer = [0u8; 64];
+    let size = server.read(&mut buffer).unwrap();
+    assert_eq!(&buffer[..size], b"hello over tls");
+
+    // a clone writes while the original is the one reading
+    let mut server_writer = server.try_clone_boxed().unwrap();
+    server_writer.write_all(b"reply").unwrap();
+    server_writer.flush().unwrap();
+    let size = client.read(&mut buffer).unwrap();
+    assert_eq!(&buffer[..size], b"reply");
+
+    server.shutdown(Shutdown::Both).unwrap();
+    assert_eq!(client.read(&mut buffer).unwrap(), 0);
+}
+
+#[test]
+fn test_tls_stream_read_timeout() {
+    let (client, server) = tls_pair("localhost");
+    let mut client = client.unwrap();
+    let _server = server.join().unwrap().unwrap();
+
+    client.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+    let mut buffer = [0u8; 16];
+    let error = client.read(&mut buffer).unwrap_err();
+    assert!(matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut));
+}
+
+#[test]
+fn test_tls_stream_rejects_wrong_server_name() {
+    let (client, server) = tls_pair("example.com");
+
+    assert!(client.is_err());
+    assert!(server.join().unwrap().is_err());
+}
@@ -0,0 +1,46 @@
+use rusty_socket_core::{RsError, Utf8Validator};
+
+#[test]
+fn test_utf8_code_points_split_across_chunks() {
+    let text = "aé€😀".as_bytes();
+    for split in 0..=text.len() {
+        let mut validator = Utf8Validator::new();
+        validator.feed(&text[..split]).expect("Failed to validate first chunk");
+        validator.feed(&text[split..]).expect("Failed to validate second chunk");
+        validator.finish().expect("Failed to finish");
+    }
+
+    // one byte at a time
+    let mut validator = Utf8Validator::new();
+    for byte in text {
+        validator.feed(&[*byte]).expect("Failed to validate byte");
+    }
+    validator.finish().expect("Failed to finish");
+}
+
+#[test]
+fn test_utf8_fails_fast() {
+    let mut validator = Utf8Validator::new();
+    validator.feed(b"valid").unwrap();
+    assert!(matches!(validator.feed(&[0x61, 0xff, 0x61]), Err(RsError::InvalidUtf8)));
+
+    // a lead byte followed by a bad continuation fails before the sequence ends
+    let mut validator = Utf8Validator::new();
+    validator.feed(&[0xe2]).unwrap();
+    assert!(matches!(validator.feed(&[0x28]), Err(RsError::InvalidUtf8)));
+
+    // surrogates and code points over U+10FFFF
+    assert!(Utf8Validator::new().feed(&[0xed, 0xa0]).is_err());
+    assert!(Utf8Validator::new().feed(&[0xf4, 0x90]).is_err());
+}
+
+#[test]
+fn test_utf8_truncated_message() {
+    let mut validator = Utf8Validator::new();
+    validator.feed(&[0x61, 0xf0, 0x9f]).unwrap();
+    assert!(matches!(validator.finish(), Err(RsError::InvalidUtf8)));
+
+    // the validator can be reused afterwards
+    validator.feed("é".as_bytes()).unwrap();
+    validator.finish().unwrap();
+}
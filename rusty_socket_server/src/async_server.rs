@@ -0,0 +1,181 @@
+use std::future::Future;
+use std::io::{self, ErrorKind};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use rusty_socket_core::{AsyncSocket, AsyncStream, Role};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+
+use crate::socket_server::ACCEPT_INTERVAL;
+use crate::{HandShake, RequestReader, ResponseLine, ServerConfig, SsError};
+use crate::Result;
+
+// Serves every client as a tokio task. The handshake is the one `SocketServer`
+// runs, so `origin_policy`, the authenticator, extensions and subprotocols from
+// `ServerConfig` all apply. Routing is sync-only: a `Router` hands paths to sync
+// `Handler`s, here every path goes to the one async handler.
+pub struct AsyncSocketServer {
+    listener: TcpListener,
+    config: Arc<ServerConfig>,
+    // clients `start` is serving, including those still in the handshake
+    active: Arc<AtomicUsize>,
+}
+
+impl AsyncSocketServer {
+    pub async fn bind(address: impl ToSocketAddrs) -> Result<Self> {
+        Self::bind_with_config(address, ServerConfig::default()).await
+    }
+
+    pub async fn bind_with_config(address: impl ToSocketAddrs, config: ServerConfig) -> Result<Self> {
+        let listener = TcpListener::bind(address).await.map_err(|_| SsError::InvalidBindAddress)?;
+
+        Ok(AsyncSocketServer {
+            listener,
+            config: Arc::new(config),
+            active: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    // Accepts the next client and performs its handshake before returning, a slow
+    // client holds up the caller, `start` runs every handshake on its own task.
+    // The socket is not counted against `max_connections`.
+    pub async fn accept(&self) -> Result<(AsyncSocket<AsyncStream>, SocketAddr)> {
+        let (stream, peer_addr) = self.listener.accept().await?;
+        let socket = Self::upgrade(stream, &self.config).await?;
+
+        Ok((socket, peer_addr))
+    }
+
+    // Accepts connections forever, each one is upgraded and handed to `handler`
+    // on its own task. Clients beyond `max_connections` get a 503.
+    pub async fn start<F, Fut>(self, handler: F) -> Result<()>
+    where
+        F: Fn(AsyncSocket<AsyncStream>, SocketAddr) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        loop {
+            let (mut stream, peer_addr) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                // e.g. out of file descriptors, the clients already connected are unaffected
+                Err(e) => {
+                    eprintln!("Failed to accept connection: {}", e);
+                    tokio::time::sleep(ACCEPT_INTERVAL).await;
+                    continue;
+                }
+            };
+
+            let config = Arc::clone(&self.config);
+            let slot = match Slot::reserve(&self.active, config.max_connections) {
+                Some(slot) => slot,
+                None => {
+                    // a TLS client would not understand a plain answer, it is just disconnected
+                    if !config.is_tls() {
+                        tokio::spawn(async move {
+                            let response = ResponseLine::err_build(503, "Service Unavailable");
+                            let _ = stream.write_all(response.to_string().as_bytes()).await;
+                            let _ = stream.flush().await;
+                        });
+                    }
+                    continue;
+                }
+            };
+
+            let handler = Arc::clone(&handler);
+            tokio::spawn(async move {
+                match Self::upgrade(stream, &config).await {
+                    Ok(socket) => handler(socket, peer_addr).await,
+                    Err(e) => eprintln!("Handshake with {} failed: {}", peer_addr, e),
+                }
+                drop(slot);
+            });
+        }
+    }
+
+    // Number of clients `start` is serving, including those still in the handshake.
+    pub fn active_connections(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    // A client that does not finish its handshake within `handshake_timeout` is
+    // dropped, the same as on `SocketServer`.
+    async fn upgrade(stream: TcpStream, config: &ServerConfig) -> Result<AsyncSocket<AsyncStream>> {
+        match tokio::time::timeout(config.handshake_timeout, Self::handshake(stream, config)).await {
+            Ok(result) => result,
+            Err(_) => Err(SsError::from(io::Error::new(ErrorKind::TimedOut, "Client did not finish the handshake"))),
+        }
+    }
+
+    async fn handshake(stream: TcpStream, config: &ServerConfig) -> Result<AsyncSocket<AsyncStream>> {
+        #[cfg(feature = "tls")]
+        let mut stream = match &config.tls {
+            Some(tls_config) => AsyncStream::accept_tls(Arc::clone(tls_config), stream).await?,
+            None => AsyncStream::Plain(stream),
+        };
+        #[cfg(not(feature = "tls"))]
+        let mut stream = AsyncStream::Plain(stream);
+
+        let mut reader = RequestReader::new(config.max_handshake_size);
+        let mut buffer = [0; 1024];
+        let (client_request, buffered) = loop {
+            let size = stream.read(&mut buffer).await?;
+            if size == 0 {
+                return Err(SsError::HandshakeFailed);
+            }
+
+            match reader.push(&buffer[..size]) {
+                Ok(Some(head)) => break head,
+                Ok(None) => {}
+                Err(e) => {
+                    stream.write_all(e.response().to_string().as_bytes()).await?;
+                    stream.flush().await?;
+                    return Err(SsError::HandshakeFailed);
+                }
+            }
+        };
+
+        let handshake = HandShake::perform_with_config(&client_request, config);
+        stream.write_all(handshake.response.to_string().as_bytes()).await?;
+        stream.flush().await?;
+        if handshake.request.is_none() {
+            return Err(SsError::HandshakeFailed);
+        }
+
+        Ok(AsyncSocket::new(stream, Role::Server)
+            .buffered(&buffered)
+            .fragment_size(config.fragment_size)
+            .close_timeout(config.close_timeout)
+            .max_frame_size(config.max_frame_size)
+            .max_message_size(config.max_message_size)
+            .heartbeat(config.heartbeat_interval, config.heartbeat_timeout)
+            .extensions(handshake.extensions)
+            .with_protocol(handshake.protocol)
+            .with_identity(handshake.identity))
+    }
+}
+
+// Holds one of the `max_connections` places until the handler is done.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn reserve(active: &Arc<AtomicUsize>, max_connections: Option<usize>) -> Option<Slot> {
+        let in_use = active.fetch_add(1, Ordering::SeqCst);
+        let slot = Slot(Arc::clone(active));
+        match max_connections {
+            Some(max_connections) if in_use >= max_connections => None,
+            _ => Some(slot),
+        }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
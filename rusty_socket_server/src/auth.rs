@@ -0,0 +1,28 @@
+use rusty_socket_core::Identity;
+
+use crate::ResponseLine;
+
+// What an authenticator decided about an upgrade request, see `ServerConfig::with_authenticator`.
+pub enum AuthResult {
+    // upgrade the connection and keep this identity with it
+    Accept(Identity),
+    // answer with this response instead of upgrading
+    Reject(ResponseLine),
+}
+
+impl AuthResult {
+    pub fn accept<T: std::any::Any + Send + Sync>(identity: T) -> Self {
+        AuthResult::Accept(Identity::new(identity))
+    }
+
+    pub fn reject(status_code: u16, reason_phrase: &str) -> Self {
+        AuthResult::Reject(ResponseLine::err_build(status_code, reason_phrase))
+    }
+
+    // 401 asking the client to authenticate with `challenge`, e.g. `Bearer realm="chat"`.
+    pub fn unauthorized(challenge: &str) -> Self {
+        let mut response = ResponseLine::err_build(401, "Unauthorized");
+        response.add_header("WWW-Authenticate", challenge);
+        AuthResult::Reject(response)
+    }
+}
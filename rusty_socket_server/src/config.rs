@@ -0,0 +1,167 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rusty_socket_core::{DeflateConfig, Extension, ExtensionFactory, PerMessageDeflate, Role};
+
+use crate::{AuthResult, OriginPolicy, OverflowPolicy, RequestLine};
+
+#[cfg(feature = "tls")]
+use std::io;
+#[cfg(feature = "tls")]
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+// Picks one of the offered subprotocols we support, they are passed in the
+// client's order of preference.
+pub type ProtocolSelector = Arc<dyn Fn(&[&str]) -> Option<String> + Send + Sync>;
+
+// Looks at an upgrade request, its path, query, headers and cookies, and either
+// accepts it with an identity or rejects it with its own response.
+pub type Authenticator = Arc<dyn Fn(&RequestLine) -> AuthResult + Send + Sync>;
+
+pub struct ServerConfig {
+    // clients served at once, further ones get a 503, `None` is no limit
+    pub max_connections: Option<usize>,
+    // serve connections on this many pooled threads instead of a thread per
+    // connection, clients beyond it wait for a free worker
+    pub worker_threads: Option<usize>,
+    // how long a client may take to send its upgrade request
+    pub handshake_timeout: Duration,
+    // how long `ServerHandle::shutdown` waits for clients to answer its close
+    pub shutdown_timeout: Duration,
+    // largest upgrade request head we read, larger ones get a 431
+    pub max_handshake_size: usize,
+    // split messages to clients into frames of this size, `None` sends them whole
+    pub fragment_size: Option<usize>,
+    // limit for frames from clients, see `FrameDecoder`
+    pub max_frame_size: usize,
+    // limit for messages from clients, see `MessageAssembler`
+    pub max_message_size: usize,
+    // messages that may wait for a slow client before `overflow_policy` applies
+    pub write_queue_size: usize,
+    pub overflow_policy: OverflowPolicy,
+    // how long to wait for the client to answer our close frame
+    pub close_timeout: Duration,
+    // send a ping this often, `None` turns heartbeats off
+    pub heartbeat_interval: Option<Duration>,
+    // how long a client may leave our ping unanswered
+    pub heartbeat_timeout: Duration,
+    // accept permessage-deflate within these limits, `None` turns compression off
+    pub deflate: Option<DeflateConfig>,
+    // extensions to accept besides permessage-deflate
+    pub extensions: Vec<ExtensionFactory>,
+    // subprotocols we speak, a client offering none of them gets no protocol
+    pub protocols: Vec<String>,
+    // chooses among the supported offers, `None` takes the client's first choice
+    pub protocol_selector: Option<ProtocolSelector>,
+    // which `Origin` headers may connect, everything is accepted by default
+    pub origin_policy: OriginPolicy,
+    // runs for every request that passed the origin check, `None` accepts everyone
+    pub authenticator: Option<Authenticator>,
+    // serve `wss://` with this certificate, see `with_tls`
+    #[cfg(feature = "tls")]
+    pub tls: Option<Arc<rustls::ServerConfig>>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            max_connections: None,
+            worker_threads: None,
+            handshake_timeout: Duration::from_secs(10),
+            shutdown_timeout: Duration::from_secs(10),
+            max_handshake_size: 8192,
+            fragment_size: None,
+            max_frame_size: 16 << 20,
+            max_message_size: 64 << 20,
+            write_queue_size: 1024,
+            overflow_policy: OverflowPolicy::Disconnect,
+            close_timeout: Duration::from_secs(5),
+            heartbeat_interval: Some(Duration::from_secs(30)),
+            heartbeat_timeout: Duration::from_secs(10),
+            deflate: None,
+            extensions: Vec::new(),
+            protocols: Vec::new(),
+            protocol_selector: None,
+            origin_policy: OriginPolicy::default(),
+            authenticator: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    // Fresh instances of every extension we are willing to accept, in order of preference.
+    pub(crate) fn extensions(&self) -> Vec<Box<dyn Extension>> {
+        let mut extensions: Vec<Box<dyn Extension>> = Vec::new();
+        if let Some(deflate) = self.deflate {
+            extensions.push(Box::new(PerMessageDeflate::new(deflate, Role::Server)));
+        }
+        extensions.extend(self.extensions.iter().map(|factory| factory()));
+
+        extensions
+    }
+
+    pub fn with_protocols<P: Into<String>>(mut self, protocols: impl IntoIterator<Item = P>) -> Self {
+        self.protocols = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_protocol_selector<F>(mut self, selector: F) -> Self
+    where
+        F: Fn(&[&str]) -> Option<String> + Send + Sync + 'static,
+    {
+        self.protocol_selector = Some(Arc::new(selector));
+        self
+    }
+
+    pub fn with_authenticator<F>(mut self, authenticator: F) -> Self
+    where
+        F: Fn(&RequestLine) -> AuthResult + Send + Sync + 'static,
+    {
+        self.authenticator = Some(Arc::new(authenticator));
+        self
+    }
+
+    // Picks the subprotocol for a `Sec-WebSocket-Protocol` request header, the
+    // answer must be one the client offered.
+    pub(crate) fn select_protocol(&self, offered: &str) -> Option<String> {
+        let supported: Vec<&str> = offered
+            .split(',')
+            .map(str::trim)
+            .filter(|protocol| self.protocols.iter().any(|supported| supported == protocol))
+            .collect();
+        if supported.is_empty() {
+            return None;
+        }
+
+        match &self.protocol_selector {
+            Some(selector) => selector(&supported).filter(|protocol| supported.contains(&protocol.as_str())),
+            None => Some(supported[0].to_string()),
+        }
+    }
+
+    pub(crate) fn is_tls(&self) -> bool {
+        #[cfg(feature = "tls")]
+        if self.tls.is_some() {
+            return true;
+        }
+
+        false
+    }
+}
+
+#[cfg(feature = "tls")]
+impl ServerConfig {
+    // Accepts TLS connections with the given certificate chain and its private key.
+    pub fn with_tls(mut self, cert_chain: Vec<CertificateDer<'static>>, key: PrivateKeyDer<'static>) -> crate::Result<Self> {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let tls_config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| builder.with_no_client_auth().with_single_cert(cert_chain, key))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.tls = Some(Arc::new(tls_config));
+
+        Ok(self)
+    }
+}
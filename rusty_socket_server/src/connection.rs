@@ -1,55 +1,510 @@
-use std::net::TcpStream;
-use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr};
+use std::io::{ErrorKind, Write};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use rusty_socket_core::{DataFrame, OpCode};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use rusty_socket_core::deflate;
+use rusty_socket_core::{
+    CloseCode, CloseFrame, ConnectionStatus, DataFrame, DeflateConfig, Extensions, FrameDecoder,
+    Heartbeat, HeartbeatAction, Identity, Message, MessageAssembler, OpCode, Role, RsError, RsResult, Transport,
+};
 
-pub struct Connection {}
+use crate::prepared;
+use crate::write_queue::{Outbox, Outgoing, Pushed, WriteQueue};
+use crate::{Handler, PreparedMessage, Registry, RouteMatch, ServerConfig, SsError};
+use crate::Result;
+
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+// Identifies a connection for the lifetime of the process, ids are never reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnectionId(u64);
+
+impl ConnectionId {
+    fn next() -> Self {
+        ConnectionId(NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ConnectionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+// A snapshot of what is known about a connection, see `Registry::list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionInfo {
+    pub id: ConnectionId,
+    pub peer_addr: SocketAddr,
+    pub connected_at: SystemTime,
+    pub path: String,
+    pub protocol: Option<String>,
+    // messages waiting to be written
+    pub queued: usize,
+    pub dropped_messages: u64,
+}
+
+struct ConnectionState {
+    status: ConnectionStatus,
+    close_deadline: Option<Instant>,
+}
+
+impl ConnectionState {
+    // Moves an open connection to closing, false if it was not open anymore.
+    fn start_closing(&mut self, close_timeout: Duration) -> bool {
+        if self.status != ConnectionStatus::Open {
+            return false;
+        }
+        self.status = ConnectionStatus::Closing;
+        self.close_deadline = Some(Instant::now() + close_timeout);
+        true
+    }
+}
+
+#[derive(Clone)]
+pub struct Connection {
+    id: ConnectionId,
+    connected_at: SystemTime,
+    // only used to shut the socket down, writes go through the outbox
+    socket: Arc<Mutex<Box<dyn Transport>>>,
+    outbox: Arc<Outbox>,
+    peer_addr: SocketAddr,
+    state: Arc<Mutex<ConnectionState>>,
+    config: Arc<ServerConfig>,
+    extensions: Arc<Mutex<Extensions>>,
+    has_extensions: bool,
+    // the agreed parameters when permessage-deflate is the only extension
+    deflate: Option<DeflateConfig>,
+    protocol: Option<String>,
+    route: Arc<RouteMatch>,
+    identity: Identity,
+    registry: Registry,
+}
+
+impl PartialEq for Connection {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
 
 impl Connection {
-    pub fn handle_frames(mut stream: TcpStream, active_conn: Arc<Mutex<Vec<TcpStream>>>) {
+    pub fn new(
+        stream: &dyn Transport,
+        config: Arc<ServerConfig>,
+        extensions: Extensions,
+        protocol: Option<String>,
+        route: RouteMatch,
+        identity: Identity,
+    ) -> Result<Self> {
+        let state = ConnectionState {
+            status: ConnectionStatus::Open,
+            close_deadline: None,
+        };
+
+        let has_extensions = !extensions.is_empty();
+        let deflate = match (extensions.names().as_slice(), extensions.header()) {
+            ([deflate::EXTENSION_NAME], Some(header)) => DeflateConfig::from_response_header(&header),
+            _ => None,
+        };
+        let extensions = Arc::new(Mutex::new(extensions));
+        let queue = Arc::new(WriteQueue::new(config.write_queue_size, config.overflow_policy));
+        // everything that can fail comes before the writer thread, which only
+        // stops once the queue is closed
+        let socket = stream.try_clone_boxed()?;
+        let peer_addr = stream.peer_addr()?;
+        let writer = stream.try_clone_boxed()?;
+        let state = Arc::new(Mutex::new(state));
+        let fragment_size = config.fragment_size.unwrap_or(0);
+        {
+            let queue = Arc::clone(&queue);
+            let extensions = Arc::clone(&extensions);
+            let state = Arc::clone(&state);
+            let close_timeout = config.close_timeout;
+            thread::spawn(move || write_queued(writer, &queue, &extensions, &state, fragment_size, close_timeout));
+        }
+
+        Ok(Connection {
+            id: ConnectionId::next(),
+            connected_at: SystemTime::now(),
+            socket: Arc::new(Mutex::new(socket)),
+            outbox: Arc::new(Outbox(queue)),
+            peer_addr,
+            state,
+            config,
+            has_extensions,
+            deflate,
+            extensions,
+            protocol,
+            route: Arc::new(route),
+            identity,
+            registry: Registry::new(),
+        })
+    }
+
+    // Makes `registry()` return the registry this connection is listed in.
+    pub fn with_registry(mut self, registry: Registry) -> Self {
+        self.registry = registry;
+        self
+    }
+
+    pub fn id(&self) -> ConnectionId {
+        self.id
+    }
+
+    pub fn connected_at(&self) -> SystemTime {
+        self.connected_at
+    }
+
+    pub fn info(&self) -> ConnectionInfo {
+        ConnectionInfo {
+            id: self.id,
+            peer_addr: self.peer_addr,
+            connected_at: self.connected_at,
+            path: self.route.path.clone(),
+            protocol: self.protocol.clone(),
+            queued: self.queued(),
+            dropped_messages: self.dropped_messages(),
+        }
+    }
+
+    // frames waiting for the client to read them
+    pub fn queued(&self) -> usize {
+        self.outbox.len()
+    }
+
+    // messages the overflow policy has thrown away so far
+    pub fn dropped_messages(&self) -> u64 {
+        self.outbox.dropped()
+    }
+
+    // every connection of the server this one belongs to, including itself,
+    // for sending to other clients
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    // Joins a room of the connection's registry, see `Registry::publish`. The
+    // connection leaves its rooms when it closes.
+    pub fn join(&self, room: &str) -> bool {
+        self.registry.join(self.id, room)
+    }
+
+    pub fn leave(&self, room: &str) -> bool {
+        self.registry.leave(self.id, room)
+    }
+
+    pub fn rooms(&self) -> Vec<String> {
+        self.registry.rooms_of(self.id)
+    }
+
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    pub fn status(&self) -> ConnectionStatus {
+        self.state.lock().unwrap().status
+    }
+
+    // the route the request path matched, with its path parameters and query
+    pub fn route(&self) -> &RouteMatch {
+        &self.route
+    }
+
+    // whoever the authenticator accepted, anonymous without one
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    // subprotocol agreed in the handshake, if any
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    // whether the handshake agreed on any extensions, e.g. permessage-deflate
+    pub fn has_extensions(&self) -> bool {
+        self.has_extensions
+    }
+
+    // names of the extensions agreed in the handshake, in the order they apply
+    pub fn extensions(&self) -> Vec<String> {
+        self.extensions.lock().unwrap().names().into_iter().map(String::from).collect()
+    }
+
+    pub fn send<M: Into<Message>>(&self, message: M) -> Result<()> {
+        let message: Message = message.into();
+        if let Message::Close(close_frame) = message {
+            return self.close(close_frame.unwrap_or_else(|| CloseFrame::new(CloseCode::Normal, "")));
+        }
+
+        if message.is_control() {
+            return self.enqueue(Outgoing::Control(prepared::encode(&message)?));
+        }
+
+        self.enqueue(Outgoing::Message(message))
+    }
+
+    // Sends our close frame after whatever is queued, the connection thread finishes
+    // the handshake once the client echoes it or drops the connection after the
+    // configured close timeout.
+    pub fn close(&self, close_frame: CloseFrame) -> Result<()> {
+        if !self.state.lock().unwrap().start_closing(self.config.close_timeout) {
+            return Ok(());
+        }
+
+        self.write_frame(close_frame.to_dataframe(false))
+    }
+
+    // Drops the connection without a close handshake, the reading thread sees
+    // the end of the stream.
+    pub(crate) fn terminate(&self) {
+        let _ = self.socket.lock().unwrap().shutdown(Shutdown::Both);
+    }
+
+    // Queues the frame prepared once for many connections, falls back to `send`
+    // when this connection needs the message encoded for it alone.
+    pub fn send_prepared(&self, prepared: &PreparedMessage) -> Result<()> {
+        match prepared.frame_for(self.has_extensions, self.deflate.as_ref(), self.config.fragment_size) {
+            Some(frame_bytes) => self.enqueue(Outgoing::Encoded(frame_bytes)),
+            None => self.send(prepared.message().clone()),
+        }
+    }
+
+    // Queues a data message, a client whose queue is full is handled by the
+    // configured overflow policy.
+    fn enqueue(&self, item: Outgoing) -> Result<()> {
+        if self.status() != ConnectionStatus::Open {
+            return Err(SsError::ConnectionClosed);
+        }
+
+        match self.outbox.push(item) {
+            Pushed::Queued | Pushed::Dropped => Ok(()),
+            Pushed::Closed => Err(SsError::ConnectionClosed),
+            Pushed::Overflow => self.overflow(),
+        }
+    }
+
+    // The `Disconnect` policy, what is waiting is dropped and the client closed.
+    fn overflow(&self) -> Result<()> {
+        self.outbox.clear_messages();
+        let _ = self.close(CloseFrame::new(CloseCode::PolicyViolation, "Client is too slow"));
+        Err(SsError::QueueFull)
+    }
+
+    // `buffered` holds bytes that arrived together with the handshake.
+    pub fn handle_frames(&self, mut stream: Box<dyn Transport>, buffered: &[u8], handler: &dyn Handler) {
+        handler.on_open(self);
+
+        let peer_close = self.read_frames(stream.as_mut(), buffered, handler);
+        self.state.lock().unwrap().status = ConnectionStatus::Closed;
+        // let the writer send our close frame, shutting down also unblocks it if
+        // the client stopped reading
+        self.outbox.drain(self.config.close_timeout);
+        let _ = stream.shutdown(Shutdown::Both);
+
+        handler.on_close(self, peer_close);
+    }
+
+    fn read_frames(&self, stream: &mut dyn Transport, buffered: &[u8], handler: &dyn Handler) -> Option<CloseFrame> {
         let mut buffer = [0; 512];
+        let mut buffered = Some(buffered).filter(|buffered| !buffered.is_empty());
+        let mut decoder = FrameDecoder::with_role(Role::Server);
+        decoder.set_reserved_bits(self.extensions.lock().unwrap().rsv_bits());
+        decoder.set_max_frame_size(self.config.max_frame_size);
+        let mut assembler = MessageAssembler::new()
+            .with_extensions(Arc::clone(&self.extensions))
+            .with_max_message_size(self.config.max_message_size);
+        let mut heartbeat = self
+            .config
+            .heartbeat_interval
+            .map(|interval| Heartbeat::new(interval, self.config.heartbeat_timeout));
         loop {
-            match stream.read(&mut buffer) {
-                Ok(size) => {
-                    if size == 0 {
-                        break;
-                    }
-
-                    let received_frame: DataFrame = DataFrame::try_from(&buffer[..size]).unwrap();
-                    let received_data: String = String::from_utf8(received_frame.payload).unwrap();
-                    println!("Received: {}", received_data);
-
-
-                    if let Some(sending_frame) =
-                        DataFrame::from_data(&received_data, OpCode::Text, false)
-                    {
-                        let frame_bytes = Vec::from(sending_frame);
-
-                        match active_conn.try_lock() {
-                            Ok(mut connections) => {
-                                for connection in connections.iter_mut() {
-                                    if let Err(e) = connection.write_all(&frame_bytes) {
-                                        eprintln!("Failed to write to connection: {}", e);
-                                    }
-                                    if let Err(e) = connection.flush() {
-                                        eprintln!("Failed to flush connection: {}", e);
-                                    }
-                                }
-                            },
-                            Err(e) => {
-                                eprintln!("Failed to lock active connections: {}", e);
-                                continue;
+            let close_deadline = self.state.lock().unwrap().close_deadline;
+            let read_timeout = match (close_deadline, heartbeat.as_mut()) {
+                (Some(deadline), _) => {
+                    if Instant::now() >= deadline {
+                        let error = std::io::Error::new(ErrorKind::TimedOut, "Client did not answer close frame");
+                        handler.on_error(self, &SsError::from(error));
+                        return None;
+                    }
+                    deadline.saturating_duration_since(Instant::now())
+                }
+                (None, Some(heartbeat)) => {
+                    match heartbeat.poll(Instant::now()) {
+                        HeartbeatAction::SendPing => {
+                            if let Err(e) = self.write_frame(DataFrame::from_data(b"", OpCode::Ping, false)) {
+                                handler.on_error(self, &e);
+                                return None;
                             }
-                        };
-
-                        println!("Data broadcast to all clients");
+                        }
+                        HeartbeatAction::PeerDead => {
+                            let error = std::io::Error::new(ErrorKind::TimedOut, "Client did not answer ping");
+                            handler.on_error(self, &SsError::from(error));
+                            return None;
+                        }
+                        HeartbeatAction::Idle => {}
                     }
+                    heartbeat.remaining(Instant::now())
                 }
+                // wake up now and then so a close started from another thread is noticed
+                (None, None) => self.config.close_timeout,
+            };
+            if let Err(e) = stream.set_read_timeout(Some(read_timeout)) {
+                handler.on_error(self, &SsError::from(e));
+                return None;
+            }
+
+            let data = match buffered.take() {
+                Some(data) => data,
+                None => match stream.read(&mut buffer) {
+                    Ok(0) => return None,
+                    Ok(size) => &buffer[..size],
+                    Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                        continue;
+                    }
+                    Err(e) => {
+                        handler.on_error(self, &SsError::from(e));
+                        return None;
+                    }
+                },
+            };
+
+            let received_frames: Vec<DataFrame> = match decoder.feed(data) {
+                Ok(frames) => frames,
                 Err(e) => {
-                    eprintln!("Failed to read WebSocket frame: {}", e);
-                    break;
+                    self.fail(handler, e);
+                    return None;
+                }
+            };
+
+            for received_frame in received_frames {
+                let message = match assembler.push(received_frame) {
+                    Ok(Some(message)) => message,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        self.fail(handler, e);
+                        return None;
+                    }
+                };
+
+                let status = self.status();
+                match &message {
+                    Message::Close(peer_close) => {
+                        if status == ConnectionStatus::Open {
+                            // answer the client's close
+                            let code = peer_close.as_ref().map_or(CloseCode::Normal, |close| close.code);
+                            let _ = self.close(CloseFrame::new(code, ""));
+                        }
+                        return peer_close.clone();
+                    }
+                    Message::Ping(payload) => {
+                        if let Err(e) = self.write_frame(DataFrame::from_data(payload, OpCode::Pong, false)) {
+                            handler.on_error(self, &e);
+                        }
+                    }
+                    Message::Pong(_) => {
+                        if let Some(heartbeat) = heartbeat.as_mut() {
+                            heartbeat.pong_received();
+                        }
+                    }
+                    Message::Text(_) | Message::Binary(_) => {}
+                }
+
+                // see `ConnectionStatus` for what happens while closing
+                if status == ConnectionStatus::Open {
+                    handler.on_message(self, message);
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    // Closes with the error as reason, the client is not waited for.
+    fn fail(&self, handler: &dyn Handler, error: RsError) {
+        let close_frame = CloseFrame::new(error.close_code(), &error.to_string());
+        handler.on_error(self, &SsError::from(error));
+        let _ = self.close(close_frame);
+    }
+
+    // Queues a control frame, those are sent even while closing.
+    fn write_frame(&self, frame: Option<DataFrame>) -> Result<()> {
+        let frame = frame.ok_or(SsError::ProtocolError(RsError::InvalidOpCode))?;
+        let item = match frame.get_opcode() {
+            OpCode::ConnectionClose => Outgoing::Close(Vec::from(frame)),
+            _ => Outgoing::Control(Vec::from(frame)),
+        };
+        match self.outbox.push(item) {
+            Pushed::Closed => Err(SsError::ConnectionClosed),
+            Pushed::Overflow => self.overflow(),
+            Pushed::Queued | Pushed::Dropped => Ok(()),
+        }
+    }
+}
+
+// Runs on the connection's writer thread until the queue is closed and empty.
+// Data messages pass through the extensions here, so one the overflow policy
+// dropped never touched their state.
+fn write_queued(
+    mut writer: Box<dyn Transport>,
+    queue: &WriteQueue,
+    extensions: &Mutex<Extensions>,
+    state: &Mutex<ConnectionState>,
+    fragment_size: usize,
+    close_timeout: Duration,
+) {
+    while let Some(item) = queue.pop() {
+        let result = match item {
+            Outgoing::Message(message) => match write_message(writer.as_mut(), &message, extensions, fragment_size) {
+                Err(RsError::IoError(e)) => Err(e),
+                // an extension could not encode the message, rather than let the
+                // client miss it the connection fails with 1011
+                Err(error) => {
+                    if !state.lock().unwrap().start_closing(close_timeout) {
+                        continue;
+                    }
+                    let close_frame = CloseFrame::new(CloseCode::InternalError, &error.to_string());
+                    if let Some(frame) = close_frame.to_dataframe(false) {
+                        let _ = writer.write_all(&Vec::from(frame)).and_then(|_| writer.flush());
+                    }
+                    break;
+                }
+                Ok(()) => Ok(()),
+            },
+            Outgoing::Encoded(frame_bytes) => writer.write_all(&frame_bytes),
+            Outgoing::Control(frame_bytes) | Outgoing::Close(frame_bytes) => writer.write_all(&frame_bytes),
+        };
+        if result.and_then(|_| writer.flush()).is_err() {
+            break;
+        }
+    }
+
+    queue.writer_finished();
+}
+
+// Without extensions the frames are written straight from the message's payload,
+// only messages an extension transforms are built up as frames first.
+fn write_message(
+    writer: &mut dyn Transport,
+    message: &Message,
+    extensions: &Mutex<Extensions>,
+    fragment_size: usize,
+) -> RsResult<()> {
+    let frames = {
+        let mut extensions = extensions.lock().unwrap();
+        if extensions.is_empty() || message.is_control() {
+            None
+        } else {
+            Some(extensions.to_frames(message, false, fragment_size)?)
+        }
+    };
+
+    match frames {
+        Some(frames) => Ok(frames.iter().try_for_each(|frame| frame.write_to(&mut *writer))?),
+        None => message.encode_into(writer, false, fragment_size),
+    }
+}
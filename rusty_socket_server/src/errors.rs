@@ -1,9 +1,16 @@
 use std::{fmt, io};
 
+use rusty_socket_core::RsError;
+
 #[derive(Debug)]
 pub enum SsError {
     InvalidBindAddress,
     IoError(io::Error),
+    ProtocolError(RsError),
+    ConnectionClosed,
+    HandshakeFailed,
+    UnknownConnection,
+    QueueFull,
 }
 
 impl fmt::Display for SsError {
@@ -11,6 +18,11 @@ impl fmt::Display for SsError {
         match self {
             Self::InvalidBindAddress => write!(f, "Cannot bind to provided address"),
             Self::IoError(e) => write!(f, "{}", e),
+            Self::ProtocolError(e) => write!(f, "{}", e),
+            Self::ConnectionClosed => write!(f, "Connection is closed"),
+            Self::HandshakeFailed => write!(f, "WebSocket handshake failed"),
+            Self::UnknownConnection => write!(f, "No open connection with this id"),
+            Self::QueueFull => write!(f, "Client fell too far behind and was disconnected"),
         }
     }
 }
@@ -21,5 +33,11 @@ impl From<io::Error> for SsError {
     }
 }
 
+impl From<RsError> for SsError {
+    fn from(error: RsError) -> Self {
+        SsError::ProtocolError(error)
+    }
+}
+
 impl std::error::Error for SsError {}
 
@@ -0,0 +1,53 @@
+use rusty_socket_core::{CloseFrame, Message};
+
+use crate::{Connection, Handler, Registry, SsError};
+
+pub struct BroadcastHandler {
+    connections: Registry,
+}
+
+impl Default for BroadcastHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BroadcastHandler {
+    pub fn new() -> Self {
+        BroadcastHandler {
+            connections: Registry::new(),
+        }
+    }
+}
+
+impl Handler for BroadcastHandler {
+    fn on_open(&self, connection: &Connection) {
+        println!("Client connected: {}", connection.peer_addr());
+        self.connections.insert(connection);
+    }
+
+    fn on_message(&self, _connection: &Connection, message: Message) {
+        if !matches!(message, Message::Text(_) | Message::Binary(_)) {
+            return;
+        }
+        println!("Received: {}", message);
+
+        for (id, e) in self.connections.broadcast(message) {
+            eprintln!("Failed to write to connection {}: {}", id, e);
+        }
+
+        println!("Data broadcast to all clients");
+    }
+
+    fn on_close(&self, connection: &Connection, close_frame: Option<CloseFrame>) {
+        match close_frame {
+            Some(close_frame) => println!("Client closed connection: {}", close_frame),
+            None => println!("Client closed connection"),
+        }
+        self.connections.remove(connection.id());
+    }
+
+    fn on_error(&self, connection: &Connection, error: &SsError) {
+        eprintln!("Connection {} failed: {}", connection.peer_addr(), error);
+    }
+}
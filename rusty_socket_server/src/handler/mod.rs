@@ -0,0 +1,26 @@
+pub mod broadcast;
+
+use rusty_socket_core::{CloseFrame, Message};
+
+use crate::{Connection, SsError};
+pub use broadcast::BroadcastHandler;
+
+pub trait Handler: Send + Sync + 'static {
+    fn on_open(&self, _connection: &Connection) {}
+
+    fn on_message(&self, connection: &Connection, message: Message);
+
+    // `close_frame` is what the client sent, `None` when it left without one
+    fn on_close(&self, _connection: &Connection, _close_frame: Option<CloseFrame>) {}
+
+    fn on_error(&self, _connection: &Connection, _error: &SsError) {}
+}
+
+impl<F> Handler for F
+where
+    F: Fn(&Connection, Message) + Send + Sync + 'static,
+{
+    fn on_message(&self, connection: &Connection, message: Message) {
+        self(connection, message)
+    }
+}
@@ -1,43 +1,88 @@
+pub mod request_error;
 pub mod request_line;
+pub mod request_reader;
 pub mod response_line;
 
-use rusty_socket_core::ConnectionStatus;
+use rusty_socket_core::{ConnectionStatus, Extensions, Identity};
+pub use request_error::RequestError;
 pub use request_line::RequestLine;
+pub use request_reader::RequestReader;
 pub use response_line::ResponseLine;
 
+use crate::{AuthResult, ServerConfig};
+
 
 
 pub struct HandShake {
     pub request: Option<RequestLine>,
     pub response: ResponseLine,
     pub state: ConnectionStatus,
+    pub extensions: Extensions,
+    pub protocol: Option<String>,
+    pub identity: Identity,
 }
 
 impl HandShake {
     pub fn perform(full_request: &str) -> Self {
-        if let Some(request) = RequestLine::from_request(full_request.lines()) {
-            if let Some(web_socket_key) = request.headers.get("sec-websocket-key") {
-                let response = ResponseLine::build(web_socket_key);
-                HandShake {
-                    request: Some(request),
-                    response,
-                    state: ConnectionStatus::Connecting,
-                }
-            } else {
-                let response = ResponseLine::err_build(400, "Bad Request");
-                HandShake {
-                    request: None,
-                    response,
-                    state: ConnectionStatus::Closing,
-                }
-            }
-        } else {
-            let response = ResponseLine::err_build(400, "Bad Request");
-            HandShake {
-                request: None,
-                response,
-                state: ConnectionStatus::Closing,
-            }
+        Self::perform_with_config(full_request, &ServerConfig::default())
+    }
+
+    // Same as `perform` but also checks the origin, runs the authenticator and
+    // negotiates the extensions and subprotocol set up in `config`.
+    pub fn perform_with_config(full_request: &str, config: &ServerConfig) -> Self {
+        let request = match RequestLine::parse(full_request) {
+            Ok(request) => request,
+            Err(error) => return Self::rejected(error.response()),
+        };
+
+        let origin = request.headers.get("origin").map(String::as_str);
+        if !config.origin_policy.is_allowed(origin) {
+            return Self::rejected(ResponseLine::err_build(403, "Forbidden"));
+        }
+
+        let identity = match &config.authenticator {
+            Some(authenticator) => match authenticator(&request) {
+                AuthResult::Accept(identity) => identity,
+                AuthResult::Reject(response) => return Self::rejected(response),
+            },
+            None => Identity::anonymous(),
+        };
+
+        // `parse` made sure the key is there
+        let mut response = ResponseLine::build(&request.headers["sec-websocket-key"]);
+        let extensions = match request.headers.get("sec-websocket-extensions") {
+            Some(offers) => Extensions::negotiate(config.extensions(), offers),
+            None => Extensions::new(),
+        };
+        if let Some(header) = extensions.header() {
+            response.add_header("Sec-WebSocket-Extensions", &header);
+        }
+        let protocol = request
+            .headers
+            .get("sec-websocket-protocol")
+            .and_then(|offered| config.select_protocol(offered));
+        if let Some(protocol) = &protocol {
+            response.add_header("Sec-WebSocket-Protocol", protocol);
+        }
+
+        HandShake {
+            request: Some(request),
+            response,
+            state: ConnectionStatus::Connecting,
+            extensions,
+            protocol,
+            identity,
+        }
+    }
+
+    fn rejected(response: ResponseLine) -> Self {
+        HandShake {
+            request: None,
+            response,
+            state: ConnectionStatus::Closing,
+            extensions: Extensions::new(),
+            protocol: None,
+            identity: Identity::anonymous(),
         }
     }
 }
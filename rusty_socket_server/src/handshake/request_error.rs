@@ -0,0 +1,50 @@
+use std::fmt;
+
+use crate::ResponseLine;
+
+// Why an upgrade request was refused, each maps to the response the client gets.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RequestError {
+    // 400, the request is not valid HTTP or misses a handshake header
+    Malformed(&'static str),
+    // 405, only `GET` can be upgraded
+    MethodNotAllowed,
+    // 426, not a WebSocket upgrade or a version other than 13
+    UpgradeRequired(&'static str),
+    // 431, the request head is larger than `ServerConfig.max_handshake_size`
+    TooLarge,
+}
+
+impl RequestError {
+    pub fn response(&self) -> ResponseLine {
+        match self {
+            Self::Malformed(_) => ResponseLine::err_build(400, "Bad Request"),
+            Self::MethodNotAllowed => {
+                let mut response = ResponseLine::err_build(405, "Method Not Allowed");
+                response.add_header("Allow", "GET");
+                response
+            }
+            Self::UpgradeRequired(_) => {
+                let mut response = ResponseLine::err_build(426, "Upgrade Required");
+                response.add_header("Upgrade", "websocket");
+                response.add_header("Connection", "Upgrade");
+                response.add_header("Sec-WebSocket-Version", "13");
+                response
+            }
+            Self::TooLarge => ResponseLine::err_build(431, "Request Header Fields Too Large"),
+        }
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(reason) => write!(f, "{}", reason),
+            Self::MethodNotAllowed => write!(f, "Only GET requests can be upgraded"),
+            Self::UpgradeRequired(reason) => write!(f, "{}", reason),
+            Self::TooLarge => write!(f, "Request head is too large"),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
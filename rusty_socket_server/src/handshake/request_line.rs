@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 use std::str::Lines;
 
+use super::RequestError;
+use crate::router::parse_query;
+
 pub struct RequestLine {
     pub resource: String,
     pub headers: HashMap<String, String>,
@@ -14,71 +17,147 @@ impl RequestLine {
         }
     }
 
-    pub fn from_request(mut full_request: Lines) -> Option<Self> {
-        let mut resource = String::new();
-        let mut headers: HashMap<String, String> = HashMap::new();
+    // the resource without its query string
+    pub fn path(&self) -> &str {
+        self.resource.split(['?', '#']).next().unwrap_or("")
+    }
 
-        if let Some(first_line) = full_request.next() {
-            let req_line: Vec<&str> = first_line.split_whitespace().collect();
-            if req_line.len() != 3 && req_line[0] != "GET" {
-                return None;
-            }
-            resource = req_line[1].to_string();
+    pub fn query(&self) -> HashMap<String, String> {
+        let resource = self.resource.split('#').next().unwrap_or("");
+        match resource.split_once('?') {
+            Some((_, query)) => parse_query(query),
+            None => HashMap::new(),
+        }
+    }
+
+    // pairs from the `Cookie` header
+    pub fn cookies(&self) -> HashMap<String, String> {
+        let cookies = self.headers.get("cookie").map(String::as_str).unwrap_or("");
+        cookies
+            .split(';')
+            .filter_map(|cookie| cookie.trim().split_once('='))
+            .map(|(name, value)| (name.to_string(), value.trim_matches('"').to_string()))
+            .collect()
+    }
+
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        self.cookies().remove(name)
+    }
+
+    // Values of a comma separated header such as `Connection: keep-alive, Upgrade`.
+    pub fn header_values(&self, name: &str) -> Vec<&str> {
+        match self.headers.get(&name.to_ascii_lowercase()) {
+            Some(values) => values.split(',').map(str::trim).filter(|value| !value.is_empty()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn has_token(&self, name: &str, token: &str) -> bool {
+        self.header_values(name).iter().any(|value| value.eq_ignore_ascii_case(token))
+    }
+
+    pub fn parse(head: &str) -> Result<Self, RequestError> {
+        Self::from_request(head.lines())
+    }
+
+    // Parses the request line and headers up to the first empty line. Header
+    // names are lowercased and repeated headers are joined into one value.
+    pub fn from_request(mut full_request: Lines) -> Result<Self, RequestError> {
+        let first_line = full_request.next().unwrap_or("");
+        let req_line: Vec<&str> = first_line.split_whitespace().collect();
+        if req_line.len() != 3 {
+            return Err(RequestError::Malformed("Invalid request line"));
+        }
+        if req_line[0] != "GET" {
+            return Err(RequestError::MethodNotAllowed);
+        }
+        if !req_line[1].starts_with('/') {
+            return Err(RequestError::Malformed("Invalid request target"));
+        }
+        if !is_http_1_1(req_line[2]) {
+            return Err(RequestError::Malformed("Unsupported HTTP version"));
         }
 
+        let mut headers: HashMap<String, String> = HashMap::new();
         for line in full_request {
             if line.is_empty() {
                 break;
             }
-
-            if let Some((key, value)) = line.split_once(": ") {
-                let l_key = key.to_ascii_lowercase();
-                headers.insert(l_key, value.to_string());
+            if line.starts_with([' ', '\t']) {
+                return Err(RequestError::Malformed("Folded header line"));
             }
-        }
 
-        match Self::validate_headers(&headers) {
-            Ok(_) => Some(RequestLine { resource, headers }),
-            Err(e) => {
-                println!("Error: {}", e);
-                None
+            let (key, value) = line
+                .split_once(':')
+                .ok_or(RequestError::Malformed("Invalid header line"))?;
+            if key.is_empty() || !key.bytes().all(is_token_byte) {
+                return Err(RequestError::Malformed("Invalid header name"));
             }
-        }
-    }
 
-    fn validate_headers(headers: &HashMap<String, String>) -> Result<(), &'static str> {
-        // println!("{:?}", headers);
-        //validate version
-        if let Some(version) = headers.get("sec-websocket-version") {
-            match version.parse::<u8>() {
-                Ok(v) => {
-                    if v != 13 {
-                        return Err("Invalid WebSocket Version");
-                    }
+            let l_key = key.to_ascii_lowercase();
+            let value = value.trim();
+            match headers.get_mut(&l_key) {
+                Some(existing) => {
+                    existing.push_str(if l_key == "cookie" { "; " } else { ", " });
+                    existing.push_str(value);
+                }
+                None => {
+                    headers.insert(l_key, value.to_string());
                 }
-                Err(_) => return Err("Invalid WebSocket Version"),
             }
-        } else {
-            return Err("Missing WebSocket Version");
         }
 
-        //Validate Upgrade
-        if let Some(upgrade_value) = headers.get("upgrade") {
-            if upgrade_value.to_ascii_lowercase() != "websocket" {
-                return Err("Invalid Upgrade Value");
-            }
-        } else {
-            return Err("Missing Upgrade Header");
+        let request = RequestLine {
+            resource: req_line[1].to_string(),
+            headers,
+        };
+        request.validate_headers()?;
+
+        Ok(request)
+    }
+
+    fn validate_headers(&self) -> Result<(), RequestError> {
+        if !self.headers.contains_key("host") {
+            return Err(RequestError::Malformed("Missing Host Header"));
         }
-        //validate Connection
-        if let Some(connection_value) = headers.get("connection") {
-            if connection_value.to_ascii_lowercase() != "upgrade" {
-                return Err("Invalid Connection Value");
-            }
-        } else {
-            return Err("Missing Connection Header");
+        if !self.has_token("upgrade", "websocket") {
+            return Err(RequestError::UpgradeRequired("Missing or invalid Upgrade Header"));
         }
+        if !self.has_token("connection", "upgrade") {
+            return Err(RequestError::Malformed("Missing or invalid Connection Header"));
+        }
+        if self.headers.get("sec-websocket-version").map(String::as_str) != Some("13") {
+            return Err(RequestError::UpgradeRequired("Unsupported WebSocket Version"));
+        }
+
+        // the key is 16 random bytes in base64
+        match self.headers.get("sec-websocket-key") {
+            Some(key) if is_websocket_key(key) => Ok(()),
+            Some(_) => Err(RequestError::Malformed("Invalid WebSocket Key")),
+            None => Err(RequestError::Malformed("Missing WebSocket Key")),
+        }
+    }
+}
 
-        Ok(())
+// `HTTP/1.1` or any later version
+fn is_http_1_1(version: &str) -> bool {
+    let version = match version.strip_prefix("HTTP/") {
+        Some(version) => version,
+        None => return false,
+    };
+    let (major, minor) = version.split_once('.').unwrap_or((version, "0"));
+    match (major.parse::<u8>(), minor.parse::<u8>()) {
+        (Ok(major), Ok(minor)) => (major, minor) >= (1, 1),
+        _ => false,
     }
 }
+
+fn is_token_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+fn is_websocket_key(key: &str) -> bool {
+    key.len() == 24
+        && key.ends_with("==")
+        && key[..22].bytes().all(|byte| byte.is_ascii_alphanumeric() || byte == b'+' || byte == b'/')
+}
@@ -0,0 +1,57 @@
+use super::RequestError;
+
+// Collects the request head from however many reads it takes to arrive.
+pub struct RequestReader {
+    buffer: Vec<u8>,
+    max_size: usize,
+    // bytes already searched for the end of the head
+    scanned: usize,
+}
+
+impl RequestReader {
+    pub fn new(max_size: usize) -> Self {
+        RequestReader {
+            buffer: Vec::new(),
+            max_size,
+            scanned: 0,
+        }
+    }
+
+    // Adds bytes read from the client. Once the blank line ending the head has
+    // arrived returns the head and whatever the client sent after it, those
+    // bytes already belong to frames.
+    pub fn push(&mut self, data: &[u8]) -> Result<Option<(String, Vec<u8>)>, RequestError> {
+        self.buffer.extend_from_slice(data);
+
+        let start = self.scanned.saturating_sub(3);
+        match head_end(&self.buffer[start..]).map(|end| start + end) {
+            Some(end) if end > self.max_size => Err(RequestError::TooLarge),
+            Some(end) => {
+                let rest = self.buffer.split_off(end);
+                let head = String::from_utf8_lossy(&self.buffer).into_owned();
+                self.buffer.clear();
+                self.scanned = 0;
+                Ok(Some((head, rest)))
+            }
+            None if self.buffer.len() > self.max_size => Err(RequestError::TooLarge),
+            None => {
+                self.scanned = self.buffer.len();
+                Ok(None)
+            }
+        }
+    }
+}
+
+// Position just past the empty line, lines may end in `\r\n` or a bare `\n`.
+fn head_end(data: &[u8]) -> Option<usize> {
+    data.iter().enumerate().find_map(|(i, byte)| {
+        if *byte != b'\n' {
+            return None;
+        }
+        match data.get(i + 1..) {
+            Some([b'\n', ..]) => Some(i + 2),
+            Some([b'\r', b'\n', ..]) => Some(i + 3),
+            _ => None,
+        }
+    })
+}
@@ -36,6 +36,12 @@ impl ResponseLine {
         }
     }
 
+    pub fn add_header(&mut self, key: &str, value: &str) {
+        self.headers
+            .get_or_insert_with(HashMap::new)
+            .insert(key.to_string(), value.to_string());
+    }
+
     fn generate_websocket_accept_key(key: &str) -> String {
         let mut hasher = SHA1::new();
         let mut combined_key = key.to_string();
@@ -1,12 +1,41 @@
+#[cfg(feature = "tokio")]
+pub mod async_server;
+pub mod auth;
+pub mod config;
 pub mod connection;
 pub mod socket_server;
 pub mod errors;
+pub mod handler;
 pub mod handshake;
+pub mod origin;
+pub mod pool;
+pub mod prepared;
+pub mod registry;
+pub mod router;
+pub mod server_handle;
+pub mod write_queue;
 
 
 pub use socket_server::SocketServer;
+pub use server_handle::ServerHandle;
+#[cfg(feature = "tokio")]
+pub use async_server::AsyncSocketServer;
+#[cfg(feature = "tokio")]
+pub use rusty_socket_core::{AsyncReceiver, AsyncSender, AsyncSocket, AsyncStream};
+pub use auth::AuthResult;
+pub use config::{Authenticator, ProtocolSelector, ServerConfig};
 pub use errors::SsError;
-pub use handshake::{HandShake, RequestLine, ResponseLine};
-pub use connection::Connection;
+pub use handler::{BroadcastHandler, Handler};
+pub use handshake::{HandShake, RequestError, RequestLine, RequestReader, ResponseLine};
+pub use connection::{Connection, ConnectionId, ConnectionInfo};
+pub use origin::OriginPolicy;
+pub use pool::ThreadPool;
+pub use prepared::PreparedMessage;
+pub use registry::Registry;
+pub use write_queue::OverflowPolicy;
+pub use router::{RouteMatch, Router};
+pub use rusty_socket_core::{CloseCode, CloseFrame, Identity, Message};
+#[cfg(feature = "tls")]
+pub use rusty_socket_core::rustls;
 
 pub type Result<T> = std::result::Result<T, SsError>;
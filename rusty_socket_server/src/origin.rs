@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+enum OriginRule {
+    Any,
+    // exact origins, or `scheme://*.domain` patterns matching any subdomain
+    List(Vec<String>),
+    Custom(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+// Decides which `Origin` headers may open a connection. Browsers always send
+// one, so checking it keeps other sites from opening sockets with our users'
+// cookies (cross-site WebSocket hijacking).
+pub struct OriginPolicy {
+    rule: OriginRule,
+    allow_missing: bool,
+}
+
+impl Default for OriginPolicy {
+    fn default() -> Self {
+        Self::any()
+    }
+}
+
+impl OriginPolicy {
+    // Accepts every origin and requests without one.
+    pub fn any() -> Self {
+        OriginPolicy {
+            rule: OriginRule::Any,
+            allow_missing: true,
+        }
+    }
+
+    // Accepts the listed origins, e.g. `https://app.example.com`, an entry such
+    // as `https://*.example.com` accepts every subdomain but not the domain itself.
+    pub fn allow<S: Into<String>>(origins: impl IntoIterator<Item = S>) -> Self {
+        let origins = origins.into_iter().map(|origin| origin.into().to_ascii_lowercase()).collect();
+        OriginPolicy {
+            rule: OriginRule::List(origins),
+            allow_missing: false,
+        }
+    }
+
+    pub fn custom<F>(predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        OriginPolicy {
+            rule: OriginRule::Custom(Arc::new(predicate)),
+            allow_missing: false,
+        }
+    }
+
+    // Whether requests without an `Origin` header, usually non-browser clients,
+    // are let through.
+    pub fn allow_missing(mut self, allow_missing: bool) -> Self {
+        self.allow_missing = allow_missing;
+        self
+    }
+
+    pub fn is_allowed(&self, origin: Option<&str>) -> bool {
+        let origin = match origin {
+            Some(origin) => origin.trim(),
+            None => return self.allow_missing,
+        };
+
+        match &self.rule {
+            OriginRule::Any => true,
+            OriginRule::List(origins) => {
+                let origin = origin.to_ascii_lowercase();
+                origins.iter().any(|allowed| origin_matches(allowed, &origin))
+            }
+            OriginRule::Custom(predicate) => predicate(origin),
+        }
+    }
+}
+
+fn origin_matches(allowed: &str, origin: &str) -> bool {
+    let (scheme, host) = match allowed.split_once("://*.") {
+        Some(wildcard) => wildcard,
+        None => return allowed == origin,
+    };
+
+    // the subdomain part must be a real host label, not a path or a port
+    match origin
+        .strip_prefix(scheme)
+        .and_then(|rest| rest.strip_prefix("://"))
+        .and_then(|rest| rest.strip_suffix(host))
+        .and_then(|rest| rest.strip_suffix('.'))
+    {
+        Some(subdomain) => {
+            !subdomain.is_empty()
+                && subdomain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        }
+        None => false,
+    }
+}
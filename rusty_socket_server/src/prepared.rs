@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use rusty_socket_core::{CloseFrame, DeflateConfig, FrameHeader, Message, Role, RsResult, MAX_HEADER_SIZE, RSV1};
+
+struct Prepared {
+    message: Message,
+    // the message as one unmasked frame
+    plain: Arc<[u8]>,
+    // the frame compressed on its own, with the window bits it needs
+    compressed: Option<(u8, Arc<[u8]>)>,
+}
+
+// A server-to-client message encoded once into shared bytes, so it can be sent
+// to any number of connections without encoding it again. Cloning is cheap.
+//
+// Connections without extensions get the plain frame. Those that agreed on
+// permessage-deflate with `server_no_context_takeover` and a large enough window
+// get the compressed one, other permessage-deflate connections the plain one.
+// Connections with other extensions, or a `fragment_size` the payload exceeds,
+// encode the message themselves as if it was sent with `Connection::send`.
+#[derive(Clone)]
+pub struct PreparedMessage {
+    prepared: Arc<Prepared>,
+}
+
+impl PreparedMessage {
+    pub fn new<M: Into<Message>>(message: M) -> Self {
+        let message: Message = message.into();
+        PreparedMessage {
+            prepared: Arc::new(Prepared {
+                // only data messages are sent from `plain`, a control message
+                // that does not fit a frame fails when a connection sends it
+                plain: Arc::from(encode(&message).unwrap_or_default()),
+                message,
+                compressed: None,
+            }),
+        }
+    }
+
+    // Also compresses the message with a fresh context and the server window bits
+    // of `deflate`, usually `ServerConfig::deflate`. Control messages stay as they are.
+    pub fn compressed<M: Into<Message>>(message: M, deflate: DeflateConfig) -> RsResult<Self> {
+        let message: Message = message.into();
+        let compressed = if message.is_control() {
+            None
+        } else {
+            Some(compress(&message, deflate)?)
+        };
+
+        Ok(PreparedMessage {
+            prepared: Arc::new(Prepared {
+                // only data messages are sent from `plain`, a control message
+                // that does not fit a frame fails when a connection sends it
+                plain: Arc::from(encode(&message).unwrap_or_default()),
+                message,
+                compressed,
+            }),
+        })
+    }
+
+    pub fn message(&self) -> &Message {
+        &self.prepared.message
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.prepared.compressed.is_some()
+    }
+
+    // The encoded frame for a connection that agreed on these extensions, `None`
+    // if the connection has to encode the message itself.
+    pub(crate) fn frame_for(
+        &self,
+        has_extensions: bool,
+        deflate: Option<&DeflateConfig>,
+        fragment_size: Option<usize>,
+    ) -> Option<Arc<[u8]>> {
+        let message = &self.prepared.message;
+        if message.is_control() {
+            return None;
+        }
+        if fragment_size.is_some_and(|fragment_size| fragment_size > 0 && message.payload().len() > fragment_size) {
+            return None;
+        }
+        if !has_extensions {
+            return Some(Arc::clone(&self.prepared.plain));
+        }
+
+        let deflate = deflate?;
+        match &self.prepared.compressed {
+            Some((window_bits, frame))
+                if deflate.server_no_context_takeover && *window_bits <= deflate.server_max_window_bits =>
+            {
+                Some(Arc::clone(frame))
+            }
+            _ => Some(Arc::clone(&self.prepared.plain)),
+        }
+    }
+}
+
+// The message as unmasked frames the way the server sends it, also used by
+// `Connection::send` for control messages. Data messages stay in one frame.
+pub(crate) fn encode(message: &Message) -> RsResult<Vec<u8>> {
+    let mut frame = Vec::with_capacity(message.payload().len() + MAX_HEADER_SIZE);
+    message.encode_into(&mut frame, false, 0)?;
+    Ok(frame)
+}
+
+fn compress(message: &Message, deflate: DeflateConfig) -> RsResult<(u8, Arc<[u8]>)> {
+    let payload = deflate.encoder(Role::Server).compress(message.payload())?;
+    let mut header = FrameHeader::new(message.opcode(), payload.len());
+    header.rsv_bits = RSV1;
+    let mut frame = Vec::with_capacity(payload.len() + MAX_HEADER_SIZE);
+    header.write_vectored(&payload, &mut frame)?;
+
+    // zlib's smallest raw deflate window is 2^9, smaller settings use that
+    Ok((deflate.server_max_window_bits.max(9), Arc::from(frame)))
+}
+
+impl From<&PreparedMessage> for PreparedMessage {
+    fn from(prepared: &PreparedMessage) -> Self {
+        prepared.clone()
+    }
+}
+
+// everything a `Message` can be made from is prepared on the spot
+macro_rules! prepare_from {
+    ($($source:ty),*) => {
+        $(
+            impl From<$source> for PreparedMessage {
+                fn from(message: $source) -> Self {
+                    PreparedMessage::new(message)
+                }
+            }
+        )*
+    };
+}
+
+prepare_from!(Message, String, &str, &String, Vec<u8>, &[u8], CloseFrame);
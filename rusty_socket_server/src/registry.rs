@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, RwLock};
+
+use crate::{Connection, ConnectionId, ConnectionInfo, PreparedMessage, SsError};
+use crate::Result;
+
+#[derive(Default)]
+struct RegistryState {
+    connections: BTreeMap<ConnectionId, Connection>,
+    // members of every room that has any
+    rooms: HashMap<String, BTreeSet<ConnectionId>>,
+}
+
+// Open connections by id and the rooms they joined. The server keeps one for
+// all of its connections, reachable through `Connection::registry`, and handlers
+// may keep their own. Sends never hold the lock while writing, a slow client
+// cannot block the others.
+#[derive(Clone, Default)]
+pub struct Registry {
+    state: Arc<RwLock<RegistryState>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry {
+            state: Arc::new(RwLock::new(RegistryState::default())),
+        }
+    }
+
+    pub fn insert(&self, connection: &Connection) {
+        self.state.write().unwrap().connections.insert(connection.id(), connection.clone());
+    }
+
+    // Also takes the connection out of every room it joined.
+    pub fn remove(&self, id: ConnectionId) -> Option<Connection> {
+        let mut state = self.state.write().unwrap();
+        state.rooms.retain(|_, members| {
+            members.remove(&id);
+            !members.is_empty()
+        });
+        state.connections.remove(&id)
+    }
+
+    pub fn get(&self, id: ConnectionId) -> Option<Connection> {
+        self.state.read().unwrap().connections.get(&id).cloned()
+    }
+
+    pub fn contains(&self, id: ConnectionId) -> bool {
+        self.state.read().unwrap().connections.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.read().unwrap().connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.read().unwrap().connections.is_empty()
+    }
+
+    // ids in the order the connections were opened
+    pub fn ids(&self) -> Vec<ConnectionId> {
+        self.state.read().unwrap().connections.keys().copied().collect()
+    }
+
+    pub fn connections(&self) -> Vec<Connection> {
+        self.state.read().unwrap().connections.values().cloned().collect()
+    }
+
+    // peer address, connect time, path and protocol of every connection
+    pub fn list(&self) -> Vec<ConnectionInfo> {
+        self.state.read().unwrap().connections.values().map(Connection::info).collect()
+    }
+
+    pub fn send_to<M: Into<PreparedMessage>>(&self, id: ConnectionId, message: M) -> Result<()> {
+        let connection = self.get(id).ok_or(SsError::UnknownConnection)?;
+        connection.send_prepared(&message.into())
+    }
+
+    // Returns the sends that failed, ids without a connection fail with `UnknownConnection`.
+    pub fn send_to_many<M: Into<PreparedMessage>>(
+        &self,
+        ids: &[ConnectionId],
+        message: M,
+    ) -> Vec<(ConnectionId, SsError)> {
+        let mut failures = Vec::new();
+        let connections: Vec<Connection> = {
+            let state = self.state.read().unwrap();
+            ids.iter()
+                .filter_map(|id| match state.connections.get(id) {
+                    Some(connection) => Some(connection.clone()),
+                    None => {
+                        failures.push((*id, SsError::UnknownConnection));
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        failures.extend(send_all(&connections, &message.into()));
+        failures
+    }
+
+    pub fn broadcast<M: Into<PreparedMessage>>(&self, message: M) -> Vec<(ConnectionId, SsError)> {
+        send_all(&self.connections(), &message.into())
+    }
+
+    // Sends to everyone but `except`, usually the connection the message came from.
+    pub fn broadcast_except<M: Into<PreparedMessage>>(
+        &self,
+        except: ConnectionId,
+        message: M,
+    ) -> Vec<(ConnectionId, SsError)> {
+        let connections: Vec<Connection> = self
+            .state
+            .read()
+            .unwrap()
+            .connections
+            .values()
+            .filter(|connection| connection.id() != except)
+            .cloned()
+            .collect();
+
+        send_all(&connections, &message.into())
+    }
+
+    // Adds a registered connection to `room`, rooms exist while they have members.
+    // Returns false for an unknown connection.
+    pub fn join(&self, id: ConnectionId, room: &str) -> bool {
+        let mut state = self.state.write().unwrap();
+        if !state.connections.contains_key(&id) {
+            return false;
+        }
+
+        state.rooms.entry(room.to_string()).or_default().insert(id);
+        true
+    }
+
+    // Returns false if the connection was not in the room.
+    pub fn leave(&self, id: ConnectionId, room: &str) -> bool {
+        let mut state = self.state.write().unwrap();
+        let members = match state.rooms.get_mut(room) {
+            Some(members) => members,
+            None => return false,
+        };
+
+        let left = members.remove(&id);
+        if members.is_empty() {
+            state.rooms.remove(room);
+        }
+        left
+    }
+
+    // rooms that have members, sorted by name
+    pub fn rooms(&self) -> Vec<String> {
+        let mut rooms: Vec<String> = self.state.read().unwrap().rooms.keys().cloned().collect();
+        rooms.sort();
+        rooms
+    }
+
+    // rooms the connection joined, sorted by name
+    pub fn rooms_of(&self, id: ConnectionId) -> Vec<String> {
+        let state = self.state.read().unwrap();
+        let mut rooms: Vec<String> = state
+            .rooms
+            .iter()
+            .filter(|(_, members)| members.contains(&id))
+            .map(|(room, _)| room.clone())
+            .collect();
+        rooms.sort();
+        rooms
+    }
+
+    pub fn members(&self, room: &str) -> Vec<ConnectionId> {
+        match self.state.read().unwrap().rooms.get(room) {
+            Some(members) => members.iter().copied().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Sends to every member of `room`, returns the sends that failed.
+    pub fn publish<M: Into<PreparedMessage>>(&self, room: &str, message: M) -> Vec<(ConnectionId, SsError)> {
+        send_all(&self.room_connections(room, None), &message.into())
+    }
+
+    // Sends to every member of `room` but `except`, usually the publisher.
+    pub fn publish_except<M: Into<PreparedMessage>>(
+        &self,
+        room: &str,
+        except: ConnectionId,
+        message: M,
+    ) -> Vec<(ConnectionId, SsError)> {
+        send_all(&self.room_connections(room, Some(except)), &message.into())
+    }
+
+    fn room_connections(&self, room: &str, except: Option<ConnectionId>) -> Vec<Connection> {
+        let state = self.state.read().unwrap();
+        match state.rooms.get(room) {
+            Some(members) => members
+                .iter()
+                .filter(|id| Some(**id) != except)
+                .filter_map(|id| state.connections.get(id).cloned())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+// The message is encoded once up front, connections whose extensions need their
+// own encoding, such as compression with context takeover, fall back to `send`.
+fn send_all(connections: &[Connection], message: &PreparedMessage) -> Vec<(ConnectionId, SsError)> {
+    connections
+        .iter()
+        .filter_map(|connection| connection.send_prepared(message).err().map(|e| (connection.id(), e)))
+        .collect()
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::Handler;
+
+enum Segment {
+    Literal(String),
+    Param(String),
+    // matches the rest of the path, only allowed at the end
+    Wildcard,
+}
+
+struct Route {
+    pattern: String,
+    segments: Vec<Segment>,
+    handler: Arc<dyn Handler>,
+}
+
+// What the request path matched, handed to the handler through `Connection::route`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RouteMatch {
+    // the pattern that matched, empty for the fallback handler
+    pub pattern: String,
+    pub path: String,
+    // values of `:name` segments
+    pub params: HashMap<String, String>,
+    // the part of the path covered by a trailing `*`
+    pub wildcard: Option<String>,
+    pub query: HashMap<String, String>,
+}
+
+impl RouteMatch {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    pub fn query(&self, name: &str) -> Option<&str> {
+        self.query.get(name).map(String::as_str)
+    }
+}
+
+// Maps path patterns such as `/chat/:room` or `/feed/*` to handlers, routes are
+// tried in the order they were added.
+pub struct Router {
+    routes: Vec<Route>,
+    fallback: Option<Arc<dyn Handler>>,
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Router {
+            routes: Vec::new(),
+            fallback: None,
+        }
+    }
+
+    pub fn route(mut self, pattern: &str, handler: impl Handler) -> Self {
+        let segments = path_segments(pattern)
+            .map(|segment| match segment {
+                "*" => Segment::Wildcard,
+                _ => match segment.strip_prefix(':') {
+                    Some(name) => Segment::Param(name.to_string()),
+                    None => Segment::Literal(segment.to_string()),
+                },
+            })
+            .collect();
+
+        self.routes.push(Route {
+            pattern: pattern.to_string(),
+            segments,
+            handler: Arc::new(handler),
+        });
+        self
+    }
+
+    // Takes every path no route matched, without it those get a 404.
+    pub fn fallback(mut self, handler: impl Handler) -> Self {
+        self.fallback = Some(Arc::new(handler));
+        self
+    }
+
+    // Finds the handler for a request target such as `/chat/lobby?user=bob`.
+    pub fn resolve(&self, resource: &str) -> Option<(Arc<dyn Handler>, RouteMatch)> {
+        let resource = resource.split('#').next().unwrap_or("");
+        let (path, query) = resource.split_once('?').unwrap_or((resource, ""));
+        let segments: Vec<String> = path_segments(path).map(percent_decode).collect();
+
+        let mut route_match = RouteMatch {
+            path: path.to_string(),
+            query: parse_query(query),
+            ..RouteMatch::default()
+        };
+        for route in self.routes.iter() {
+            if let Some((params, wildcard)) = route.matches(&segments) {
+                route_match.pattern = route.pattern.clone();
+                route_match.params = params;
+                route_match.wildcard = wildcard;
+                return Some((Arc::clone(&route.handler), route_match));
+            }
+        }
+
+        self.fallback.as_ref().map(|handler| (Arc::clone(handler), route_match))
+    }
+}
+
+impl Route {
+    fn matches(&self, segments: &[String]) -> Option<(HashMap<String, String>, Option<String>)> {
+        let mut params = HashMap::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Wildcard => return Some((params, Some(segments[i..].join("/")))),
+                Segment::Literal(literal) if segments.get(i) == Some(literal) => {}
+                Segment::Param(name) => {
+                    params.insert(name.clone(), segments.get(i)?.clone());
+                }
+                Segment::Literal(_) => return None,
+            }
+        }
+
+        (self.segments.len() == segments.len()).then_some((params, None))
+    }
+}
+
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+// Splits `a=1&b=two%20words` into its pairs, a repeated key keeps its last value.
+pub fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(&key.replace('+', " ")), percent_decode(&value.replace('+', " ")))
+        })
+        .collect()
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match (bytes[i], bytes.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) if hex.iter().all(u8::is_ascii_hexdigit) => {
+                std::str::from_utf8(hex).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            }
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
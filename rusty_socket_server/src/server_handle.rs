@@ -0,0 +1,137 @@
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rusty_socket_core::{CloseCode, CloseFrame};
+
+use crate::{Connection, Registry, ServerConfig, ThreadPool};
+
+// What the accept loop, the connection threads and the handles share.
+pub(crate) struct ServerState {
+    pub(crate) config: Arc<ServerConfig>,
+    stopping: AtomicBool,
+    // clients being served, including those still in the handshake
+    active: AtomicUsize,
+    pub(crate) registry: Registry,
+    threads: Mutex<Vec<JoinHandle<()>>>,
+    pool: Mutex<Option<ThreadPool>>,
+}
+
+impl ServerState {
+    pub(crate) fn new(config: ServerConfig) -> Self {
+        ServerState {
+            pool: Mutex::new(config.worker_threads.map(ThreadPool::new)),
+            config: Arc::new(config),
+            stopping: AtomicBool::new(false),
+            active: AtomicUsize::new(0),
+            registry: Registry::new(),
+            threads: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    // Takes a place for a new client, `None` when `max_connections` are in use.
+    pub(crate) fn reserve(self: &Arc<Self>) -> Option<ConnectionSlot> {
+        let active = self.active.fetch_add(1, Ordering::SeqCst);
+        let slot = ConnectionSlot(Arc::clone(self));
+        match self.config.max_connections {
+            Some(max_connections) if active >= max_connections => None,
+            _ => Some(slot),
+        }
+    }
+
+    // Runs a connection on the worker pool or its own thread.
+    pub(crate) fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        if let Some(pool) = self.pool.lock().unwrap().as_ref() {
+            pool.execute(job);
+            return;
+        }
+
+        let mut threads = self.threads.lock().unwrap();
+        threads.retain(|thread| !thread.is_finished());
+        threads.push(thread::spawn(job));
+    }
+
+    // Lists an open connection so shutdown can close it, returns false if the
+    // server is already shutting down.
+    pub(crate) fn track(&self, connection: &Connection) -> bool {
+        self.registry.insert(connection);
+        !self.is_stopping()
+    }
+
+    pub(crate) fn untrack(&self, connection: &Connection) {
+        self.registry.remove(connection.id());
+    }
+}
+
+// Holds one of the `max_connections` places until the connection is done.
+pub(crate) struct ConnectionSlot(Arc<ServerState>);
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Stops a running `SocketServer` from another thread, see `SocketServer::handle`.
+#[derive(Clone)]
+pub struct ServerHandle {
+    state: Arc<ServerState>,
+}
+
+impl ServerHandle {
+    pub(crate) fn new(state: Arc<ServerState>) -> Self {
+        ServerHandle { state }
+    }
+
+    pub fn active_connections(&self) -> usize {
+        self.state.active()
+    }
+
+    // the server's open connections
+    pub fn registry(&self) -> &Registry {
+        &self.state.registry
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.state.is_stopping()
+    }
+
+    // Stops accepting clients and sends every open connection a Going Away close.
+    // Waits up to `shutdown_timeout` for the close handshakes, then drops the
+    // connections still open and joins the connection threads. Must not be
+    // called from a handler, it would wait for itself.
+    pub fn shutdown(&self) {
+        if self.state.stopping.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        for connection in self.state.registry.connections() {
+            let _ = connection.close(CloseFrame::new(CloseCode::GoingAway, "Server shutting down"));
+        }
+
+        let deadline = Instant::now() + self.state.config.shutdown_timeout;
+        while self.state.active() > 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        for connection in self.state.registry.connections() {
+            connection.terminate();
+        }
+
+        let threads = mem::take(&mut *self.state.threads.lock().unwrap());
+        for thread in threads {
+            let _ = thread.join();
+        }
+        let pool = self.state.pool.lock().unwrap().take();
+        drop(pool);
+    }
+}
@@ -1,81 +1,221 @@
-use std::io::{Read, Write};
+use std::io::{ErrorKind, Read, Write};
 use std::net::{SocketAddr, SocketAddrV4, TcpListener, TcpStream, ToSocketAddrs};
 use std::thread;
-use std::sync::{Mutex, Arc};
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::{Connection, HandShake, SsError};
+use rusty_socket_core::{CloseCode, CloseFrame, Transport};
+#[cfg(feature = "tls")]
+use rusty_socket_core::TlsStream;
+
+use crate::server_handle::ServerState;
+use crate::{Connection, HandShake, Handler, Registry, RequestReader, ResponseLine, Router, ServerConfig, ServerHandle, SsError};
 use crate::Result;
 
 pub struct SocketServer {
     target: SocketAddrV4,
-    active_connections: Arc<Mutex<Vec<TcpStream>>>,
+    router: Arc<Router>,
+    state: Arc<ServerState>,
 }
 
+// how often the accept loop looks for a shutdown while no client is connecting
+pub(crate) const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
 impl SocketServer {
-    pub fn build(address: impl ToSocketAddrs) -> Result<Self> {
+    pub fn build(address: impl ToSocketAddrs, handler: impl Handler) -> Result<Self> {
+        Self::build_with_config(address, handler, ServerConfig::default())
+    }
+
+    pub fn build_with_config(
+        address: impl ToSocketAddrs,
+        handler: impl Handler,
+        config: ServerConfig,
+    ) -> Result<Self> {
+        Self::build_with_router(address, Router::new().fallback(handler), config)
+    }
+
+    // Serves each path with the handler of its route, unknown paths get a 404.
+    pub fn build_with_router(
+        address: impl ToSocketAddrs,
+        router: Router,
+        config: ServerConfig,
+    ) -> Result<Self> {
         let mut addrs = address.to_socket_addrs().map_err(SsError::from)?;
         if let Some(SocketAddr::V4(target)) = addrs.next() {
             Ok( SocketServer {
                 target,
-                active_connections: Arc::new(Mutex::new(Vec::new())),
+                router: Arc::new(router),
+                state: Arc::new(ServerState::new(config)),
             })
         } else {
             Err(SsError::InvalidBindAddress)
         }
     }
 
+    // Accepts clients until `ServerHandle::shutdown` is called.
     pub fn start(&self) {
         let tcp_listener = TcpListener::bind(&self.target).unwrap();
+        tcp_listener.set_nonblocking(true).expect("Failed to set up listener");
 
         println!("Server Listening on {}", &self.target.to_string());
 
-        for stream in tcp_listener.incoming() {
-            match stream {
-                Ok(stream) => {
+        while !self.state.is_stopping() {
+            match tcp_listener.accept() {
+                Ok((stream, _)) => {
+                    if let Err(e) = stream.set_nonblocking(false) {
+                        eprintln!("Failed to set up connection: {}", e);
+                        continue;
+                    }
                     self.handle_connection(stream);
                 }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_INTERVAL),
+                // e.g. out of file descriptors, the clients already connected are unaffected
                 Err(e) => {
-                    println!("Error: {}", e);
-                    panic!("Shutting Down Server Due to Error in Stream");
+                    eprintln!("Failed to accept connection: {}", e);
+                    thread::sleep(ACCEPT_INTERVAL);
                 }
             }
         }
     }
 
+    // For stopping the server from another thread, take it before calling `start`.
+    pub fn handle(&self) -> ServerHandle {
+        ServerHandle::new(Arc::clone(&self.state))
+    }
+
+    // the open connections, by id
+    pub fn registry(&self) -> &Registry {
+        &self.state.registry
+    }
+
+    // Number of clients currently being served, including those still in the handshake.
+    pub fn active_connections(&self) -> usize {
+        self.state.active()
+    }
+
+    // Hands the client to its own thread or the worker pool, the handshake runs
+    // there so a slow client never holds up the accept loop.
     pub fn handle_connection(&self, mut stream: TcpStream) {
-        let mut buffer = [0; 512];
-        let _ = stream.read(&mut buffer);
-        let client_request = String::from_utf8_lossy(&buffer);
+        let slot = match self.state.reserve() {
+            Some(slot) => slot,
+            None => {
+                // a TLS client would not understand a plain answer, it is just disconnected
+                if !self.state.config.is_tls() {
+                    let response = ResponseLine::err_build(503, "Service Unavailable");
+                    let _ = stream.write_all(response.to_string().as_bytes());
+                    let _ = stream.flush();
+                }
+                return;
+            }
+        };
+
+        let router = Arc::clone(&self.router);
+        let state = Arc::clone(&self.state);
+        self.state.execute(move || {
+            Self::serve(stream, &router, &state);
+            drop(slot);
+        });
+    }
+
+    fn serve(stream: TcpStream, router: &Router, state: &ServerState) {
+        // still queued for a worker when the server began shutting down
+        if state.is_stopping() {
+            return;
+        }
 
-        let handshake = HandShake::perform(&client_request);
+        let config = Arc::clone(&state.config);
+        if let Err(e) = stream.set_read_timeout(Some(config.handshake_timeout)) {
+            eprintln!("Failed to set up transport: {}", e);
+            return;
+        }
+        let mut stream = match Self::wrap_transport(stream, &config) {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Failed to set up transport: {}", e);
+                return;
+            }
+        };
+
+        let mut reader = RequestReader::new(config.max_handshake_size);
+        let mut buffer = [0; 1024];
+        let (client_request, buffered) = loop {
+            let size = match stream.read(&mut buffer) {
+                Ok(0) => return,
+                Ok(size) => size,
+                Err(e) => {
+                    eprintln!("Failed to read handshake: {}", e);
+                    return;
+                }
+            };
+
+            match reader.push(&buffer[..size]) {
+                Ok(Some(head)) => break head,
+                Ok(None) => {}
+                Err(e) => {
+                    let _ = stream.write_all(e.response().to_string().as_bytes());
+                    let _ = stream.flush();
+                    return;
+                }
+            }
+        };
+
+        let handshake = HandShake::perform_with_config(&client_request, &config);
 
-        match handshake.request {
-            Some(_) => {
-                stream
+        match &handshake.request {
+            Some(request) => {
+                let (handler, route) = match router.resolve(&request.resource) {
+                    Some(found) => found,
+                    None => {
+                        let response = ResponseLine::err_build(404, "Not Found");
+                        let _ = stream.write_all(response.to_string().as_bytes());
+                        let _ = stream.flush();
+                        return;
+                    }
+                };
+
+                if let Err(e) = stream
                     .write_all(handshake.response.to_string().as_bytes())
-                    .expect("Failed to write success response");
-                stream.flush().expect("Failed to flush success stream");
+                    .and_then(|_| stream.flush())
+                {
+                    eprintln!("Failed to write handshake response: {}", e);
+                    return;
+                }
 
-                match self.active_connections.try_lock() {
-                    Ok(mut connections) => {
-                        connections.push(stream.try_clone().unwrap());
-                    },
+                let connection = match Connection::new(
+                    stream.as_ref(),
+                    config,
+                    handshake.extensions,
+                    handshake.protocol,
+                    route,
+                    handshake.identity,
+                ) {
+                    Ok(connection) => connection.with_registry(state.registry.clone()),
                     Err(e) => {
-                        panic!("Failed to lock active connections: {}", e);
+                        eprintln!("Failed to set up connection: {}", e);
+                        return;
                     }
-                }
+                };
 
-                let rc_active_conn = Arc::clone(&self.active_connections);
-                thread::spawn(move || {
-                    Connection::handle_frames(stream, rc_active_conn);
-                });
+                if !state.track(&connection) {
+                    let _ = connection.close(CloseFrame::new(CloseCode::GoingAway, "Server shutting down"));
+                }
+                connection.handle_frames(stream, &buffered, handler.as_ref());
+                state.untrack(&connection);
             }
             None => {
-                stream
-                    .write_all(handshake.response.to_string().as_bytes())
-                    .expect("Failed to write response");
-                stream.flush().expect("Failed to flush stream");
+                let _ = stream.write_all(handshake.response.to_string().as_bytes());
+                let _ = stream.flush();
             }
         }
     }
+
+    #[cfg_attr(not(feature = "tls"), allow(unused_variables))]
+    fn wrap_transport(stream: TcpStream, config: &ServerConfig) -> Result<Box<dyn Transport>> {
+        #[cfg(feature = "tls")]
+        if let Some(tls_config) = &config.tls {
+            return Ok(Box::new(TlsStream::accept(Arc::clone(tls_config), stream)?));
+        }
+
+        Ok(Box::new(stream))
+    }
 }
@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use rusty_socket_core::Message;
+
+// pings and pongs that may wait next to the data messages, more are handled by
+// the overflow policy just like data
+const CONTROL_CAPACITY: usize = 16;
+
+// What to do when a client reads slower than it is sent to and its queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // forget the oldest waiting message to make room
+    DropOldest,
+    // forget the message being sent
+    DropNewest,
+    // close the connection with 1008 Policy Violation
+    Disconnect,
+}
+
+pub(crate) enum Outgoing {
+    // encoded by the writer, after the connection's extensions
+    Message(Message),
+    // frames encoded once and shared by several connections
+    Encoded(Arc<[u8]>),
+    // pings and pongs, bound by their own small capacity
+    Control(Vec<u8>),
+    // our close frame, never dropped
+    Close(Vec<u8>),
+}
+
+impl Outgoing {
+    fn is_data(&self) -> bool {
+        matches!(self, Outgoing::Message(_) | Outgoing::Encoded(_))
+    }
+
+    fn is_close(&self) -> bool {
+        matches!(self, Outgoing::Close(_))
+    }
+}
+
+pub(crate) enum Pushed {
+    Queued,
+    Dropped,
+    Overflow,
+    Closed,
+}
+
+struct QueueState {
+    items: VecDeque<Outgoing>,
+    // no more items are accepted, the writer stops once it has written the rest
+    closed: bool,
+    writer_done: bool,
+    // data messages thrown away, pings and pongs are not counted
+    dropped: u64,
+    // running counts of the queued data messages and pings and pongs
+    data_items: usize,
+    control_items: usize,
+}
+
+impl QueueState {
+    fn queued(&self, data: bool) -> usize {
+        if data {
+            self.data_items
+        } else {
+            self.control_items
+        }
+    }
+
+    fn count(&mut self, item: &Outgoing, added: bool) {
+        let count = match item {
+            Outgoing::Close(_) => return,
+            Outgoing::Control(_) => &mut self.control_items,
+            _ => &mut self.data_items,
+        };
+        if added {
+            *count += 1;
+        } else {
+            *count -= 1;
+        }
+    }
+
+    fn remove(&mut self, index: usize) -> Option<Outgoing> {
+        let item = self.items.remove(index)?;
+        self.count(&item, false);
+        Some(item)
+    }
+}
+
+// Outgoing frames of one connection, drained by its writer thread.
+pub(crate) struct WriteQueue {
+    state: Mutex<QueueState>,
+    changed: Condvar,
+    capacity: usize,
+    policy: OverflowPolicy,
+}
+
+impl WriteQueue {
+    pub(crate) fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        WriteQueue {
+            state: Mutex::new(QueueState {
+                items: VecDeque::new(),
+                closed: false,
+                writer_done: false,
+                dropped: 0,
+                data_items: 0,
+                control_items: 0,
+            }),
+            changed: Condvar::new(),
+            capacity: capacity.max(1),
+            policy,
+        }
+    }
+
+    pub(crate) fn push(&self, item: Outgoing) -> Pushed {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return Pushed::Closed;
+        }
+
+        let data = item.is_data();
+        let capacity = if data { self.capacity } else { CONTROL_CAPACITY };
+        if !item.is_close() && state.queued(data) >= capacity {
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    let oldest = state
+                        .items
+                        .iter()
+                        .position(|queued| queued.is_data() == data && !queued.is_close());
+                    if let Some(oldest) = oldest {
+                        state.remove(oldest);
+                    }
+                    if data {
+                        state.dropped += 1;
+                    }
+                }
+                OverflowPolicy::DropNewest => {
+                    if data {
+                        state.dropped += 1;
+                    }
+                    return Pushed::Dropped;
+                }
+                OverflowPolicy::Disconnect => return Pushed::Overflow,
+            }
+        }
+
+        state.count(&item, true);
+        state.items.push_back(item);
+        self.changed.notify_all();
+        Pushed::Queued
+    }
+
+    // Forgets every waiting data message, used before closing a slow connection.
+    pub(crate) fn clear_messages(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.items.retain(|item| !item.is_data());
+        state.dropped += state.data_items as u64;
+        state.data_items = 0;
+    }
+
+    // Waits for the next item, `None` once the queue is closed and empty.
+    pub(crate) fn pop(&self) -> Option<Outgoing> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(item) = state.remove(0) {
+                self.changed.notify_all();
+                return Some(item);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.changed.wait(state).unwrap();
+        }
+    }
+
+    pub(crate) fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.changed.notify_all();
+    }
+
+    // Called by the writer when it stops, after a write error the rest is dropped.
+    pub(crate) fn writer_finished(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.writer_done = true;
+        state.items.clear();
+        state.data_items = 0;
+        state.control_items = 0;
+        self.changed.notify_all();
+    }
+
+    // Closes the queue and waits up to `timeout` for the writer to send what is left.
+    pub(crate) fn drain(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        self.changed.notify_all();
+        while !state.writer_done {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return false;
+            }
+            state = self.changed.wait_timeout(state, remaining).unwrap().0;
+        }
+
+        true
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.state.lock().unwrap().items.len()
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.state.lock().unwrap().dropped
+    }
+}
+
+// The connections' share of a queue, the writer stops once the last one is gone.
+pub(crate) struct Outbox(pub(crate) Arc<WriteQueue>);
+
+impl std::ops::Deref for Outbox {
+    type Target = WriteQueue;
+
+    fn deref(&self) -> &WriteQueue {
+        &self.0
+    }
+}
+
+impl Drop for Outbox {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
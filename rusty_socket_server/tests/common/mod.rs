@@ -0,0 +1,32 @@
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rusty_socket_core::{Extensions, Identity};
+use rusty_socket_server::{Connection, RouteMatch, ServerConfig};
+
+// A server side connection and the client end of its socket, the client gives
+// up on reads after a few seconds.
+pub fn connection_pair(
+    config: ServerConfig,
+    extensions: Extensions,
+    protocol: Option<&str>,
+    route: RouteMatch,
+) -> (Connection, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let (server, _) = listener.accept().unwrap();
+
+    let connection = Connection::new(
+        &server,
+        Arc::new(config),
+        extensions,
+        protocol.map(String::from),
+        route,
+        Identity::anonymous(),
+    )
+    .unwrap();
+
+    (connection, client)
+}
@@ -0,0 +1,147 @@
+use rusty_socket_server::{AuthResult, HandShake, OriginPolicy, RequestLine, ServerConfig};
+
+fn upgrade_request(extra_headers: &str) -> String {
+    format!(
+        "GET /graphql HTTP/1.1\r\n\
+        Host: localhost:8080\r\n\
+        Upgrade: websocket\r\n\
+        Connection: Upgrade\r\n\
+        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+        Sec-WebSocket-Version: 13\r\n\
+        {}\r\n",
+        extra_headers
+    )
+}
+
+fn protocol_header(handshake: &HandShake) -> Option<String> {
+    handshake
+        .response
+        .headers
+        .as_ref()
+        .and_then(|headers| headers.get("Sec-WebSocket-Protocol").cloned())
+}
+
+#[test]
+fn test_handshake_accepts_valid_request() {
+    let handshake = HandShake::perform(&upgrade_request(""));
+
+    assert!(handshake.request.is_some());
+    assert_eq!(handshake.response.status_code, 101);
+    let headers = handshake.response.headers.as_ref().unwrap();
+    assert_eq!(headers.get("Sec-WebSocket-Accept").unwrap(), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    assert_eq!(handshake.protocol, None);
+}
+
+#[test]
+fn test_handshake_picks_clients_first_supported_protocol() {
+    let config = ServerConfig::default().with_protocols(["graphql-transport-ws", "v2.json"]);
+
+    let handshake = HandShake::perform_with_config(
+        &upgrade_request("Sec-WebSocket-Protocol: chat, v2.json, graphql-transport-ws\r\n"),
+        &config,
+    );
+    assert_eq!(handshake.protocol.as_deref(), Some("v2.json"));
+    assert_eq!(protocol_header(&handshake).as_deref(), Some("v2.json"));
+
+    let handshake = HandShake::perform_with_config(&upgrade_request("Sec-WebSocket-Protocol: chat\r\n"), &config);
+    assert!(handshake.request.is_some());
+    assert_eq!(handshake.protocol, None);
+    assert_eq!(protocol_header(&handshake), None);
+}
+
+#[test]
+fn test_handshake_uses_protocol_selector() {
+    let config = ServerConfig::default()
+        .with_protocols(["graphql-transport-ws", "v2.json"])
+        .with_protocol_selector(|offered| {
+            offered
+                .iter()
+                .find(|protocol| **protocol == "graphql-transport-ws")
+                .map(|protocol| protocol.to_string())
+        });
+
+    let handshake = HandShake::perform_with_config(
+        &upgrade_request("Sec-WebSocket-Protocol: v2.json, graphql-transport-ws\r\n"),
+        &config,
+    );
+    assert_eq!(handshake.protocol.as_deref(), Some("graphql-transport-ws"));
+
+    // a selector cannot answer with something the client did not offer
+    let config = ServerConfig::default()
+        .with_protocols(["v2.json"])
+        .with_protocol_selector(|_| Some(String::from("v3.json")));
+    let handshake = HandShake::perform_with_config(&upgrade_request("Sec-WebSocket-Protocol: v2.json\r\n"), &config);
+    assert_eq!(handshake.protocol, None);
+}
+
+#[test]
+fn test_handshake_rejects_forbidden_origin() {
+    let config = ServerConfig {
+        origin_policy: OriginPolicy::allow(["https://app.example.com"]),
+        ..ServerConfig::default()
+    };
+
+    let handshake = HandShake::perform_with_config(&upgrade_request("Origin: https://evil.example\r\n"), &config);
+    assert!(handshake.request.is_none());
+    assert_eq!(handshake.response.status_code, 403);
+    assert!(handshake.response.to_string().starts_with("HTTP/1.1 403 Forbidden\r\n"));
+
+    let handshake = HandShake::perform_with_config(&upgrade_request(""), &config);
+    assert_eq!(handshake.response.status_code, 403);
+
+    let handshake = HandShake::perform_with_config(&upgrade_request("Origin: https://app.example.com\r\n"), &config);
+    assert_eq!(handshake.response.status_code, 101);
+}
+
+#[derive(Debug, PartialEq)]
+struct User(String);
+
+fn authenticate(request: &RequestLine) -> AuthResult {
+    let token = request.query().get("token").cloned().or_else(|| request.cookie("session"));
+    match token.as_deref() {
+        Some("secret") if request.path() == "/graphql" => AuthResult::accept(User(String::from("alice"))),
+        Some(_) => AuthResult::reject(403, "Forbidden"),
+        None => AuthResult::unauthorized("Bearer realm=\"chat\""),
+    }
+}
+
+#[test]
+fn test_handshake_runs_authenticator() {
+    let config = ServerConfig::default().with_authenticator(authenticate);
+
+    let handshake = HandShake::perform_with_config(&upgrade_request(""), &config);
+    assert!(handshake.request.is_none());
+    assert_eq!(handshake.response.status_code, 401);
+    let headers = handshake.response.headers.as_ref().unwrap();
+    assert_eq!(headers.get("WWW-Authenticate").unwrap(), "Bearer realm=\"chat\"");
+
+    let request = upgrade_request("").replace("/graphql", "/graphql?token=wrong");
+    let handshake = HandShake::perform_with_config(&request, &config);
+    assert_eq!(handshake.response.status_code, 403);
+
+    let request = upgrade_request("").replace("/graphql", "/graphql?token=secret");
+    let handshake = HandShake::perform_with_config(&request, &config);
+    assert_eq!(handshake.response.status_code, 101);
+    assert_eq!(handshake.identity.get::<User>(), Some(&User(String::from("alice"))));
+
+    let handshake = HandShake::perform_with_config(&upgrade_request("Cookie: theme=dark; session=secret\r\n"), &config);
+    assert_eq!(handshake.response.status_code, 101);
+    assert!(!handshake.identity.is_anonymous());
+
+    let handshake = HandShake::perform(&upgrade_request(""));
+    assert!(handshake.identity.is_anonymous());
+}
+
+#[test]
+fn test_request_line_path_query_and_cookies() {
+    let request = upgrade_request("Cookie: theme=dark; session=\"abc\"\r\n").replace("/graphql", "/chat/lobby?user=bob%20smith&x");
+    let request = RequestLine::from_request(request.lines()).unwrap();
+
+    assert_eq!(request.path(), "/chat/lobby");
+    let query = request.query();
+    assert_eq!(query.get("user").unwrap(), "bob smith");
+    assert_eq!(query.get("x").unwrap(), "");
+    assert_eq!(request.cookie("theme").unwrap(), "dark");
+    assert_eq!(request.cookie("session").unwrap(), "abc");
+    assert_eq!(request.cookie("missing"), None);
+}
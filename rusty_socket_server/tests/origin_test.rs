@@ -0,0 +1,47 @@
+use rusty_socket_server::OriginPolicy;
+
+#[test]
+fn test_any_origin_is_the_default() {
+    let policy = OriginPolicy::default();
+
+    assert!(policy.is_allowed(Some("https://evil.example")));
+    assert!(policy.is_allowed(None));
+}
+
+#[test]
+fn test_allow_list_matches_exact_origins() {
+    let policy = OriginPolicy::allow(["https://app.example.com", "http://localhost:8080"]);
+
+    assert!(policy.is_allowed(Some("https://app.example.com")));
+    assert!(policy.is_allowed(Some("HTTPS://App.Example.com")));
+    assert!(policy.is_allowed(Some("http://localhost:8080")));
+    assert!(!policy.is_allowed(Some("http://app.example.com")));
+    assert!(!policy.is_allowed(Some("https://app.example.com.evil.io")));
+    assert!(!policy.is_allowed(Some("http://localhost:8081")));
+    assert!(!policy.is_allowed(Some("null")));
+    assert!(!policy.is_allowed(None));
+    assert!(policy.allow_missing(true).is_allowed(None));
+}
+
+#[test]
+fn test_allow_list_wildcard_subdomains() {
+    let policy = OriginPolicy::allow(["https://*.example.com"]);
+
+    assert!(policy.is_allowed(Some("https://app.example.com")));
+    assert!(policy.is_allowed(Some("https://eu.api.example.com")));
+    assert!(!policy.is_allowed(Some("https://example.com")));
+    assert!(!policy.is_allowed(Some("https://evilexample.com")));
+    assert!(!policy.is_allowed(Some("https://.example.com")));
+    assert!(!policy.is_allowed(Some("http://app.example.com")));
+    assert!(!policy.is_allowed(Some("https://evil.com/.example.com")));
+    assert!(!policy.is_allowed(Some("https://app.example.com:8443")));
+}
+
+#[test]
+fn test_custom_origin_predicate() {
+    let policy = OriginPolicy::custom(|origin| origin.ends_with(".internal"));
+
+    assert!(policy.is_allowed(Some("http://dashboard.internal")));
+    assert!(!policy.is_allowed(Some("http://dashboard.external")));
+    assert!(!policy.is_allowed(None));
+}
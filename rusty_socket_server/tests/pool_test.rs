@@ -0,0 +1,60 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use rusty_socket_server::ThreadPool;
+
+#[test]
+fn test_thread_pool_bounds_concurrent_jobs() {
+    let pool = ThreadPool::new(2);
+    assert_eq!(pool.size(), 2);
+
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let (done_tx, done_rx) = mpsc::channel();
+    for _ in 0..6 {
+        let running = Arc::clone(&running);
+        let peak = Arc::clone(&peak);
+        let done_tx = done_tx.clone();
+        pool.execute(move || {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(20));
+            running.fetch_sub(1, Ordering::SeqCst);
+            done_tx.send(()).unwrap();
+        });
+    }
+
+    for _ in 0..6 {
+        done_rx.recv_timeout(Duration::from_secs(2)).unwrap();
+    }
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_thread_pool_survives_panicking_jobs() {
+    let pool = ThreadPool::new(1);
+    let (done_tx, done_rx) = mpsc::channel();
+
+    pool.execute(|| panic!("handler failed"));
+    pool.execute(move || done_tx.send(()).unwrap());
+
+    done_rx.recv_timeout(Duration::from_secs(2)).unwrap();
+}
+
+#[test]
+fn test_thread_pool_finishes_queued_jobs_on_drop() {
+    let pool = ThreadPool::new(1);
+    let finished = Arc::new(AtomicUsize::new(0));
+    for _ in 0..3 {
+        let finished = Arc::clone(&finished);
+        pool.execute(move || {
+            thread::sleep(Duration::from_millis(10));
+            finished.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+
+    drop(pool);
+    assert_eq!(finished.load(Ordering::SeqCst), 3);
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use std::io::Read;
+use std::net::TcpStream;
+
+use rusty_socket_core::{
+    DataFrame, DeflateConfig, Extension, Extensions, FrameDecoder, Message, OpCode, PerMessageDeflate, Role, RSV1,
+};
+use rusty_socket_server::{Connection, PreparedMessage, Registry, RouteMatch, ServerConfig};
+
+fn connection_pair(config: ServerConfig, extensions: Extensions) -> (Connection, TcpStream) {
+    common::connection_pair(config, extensions, None, RouteMatch::default())
+}
+
+fn deflate(server_no_context_takeover: bool) -> Extensions {
+    let config = DeflateConfig {
+        server_no_context_takeover,
+        ..DeflateConfig::default()
+    };
+    let candidates: Vec<Box<dyn Extension>> = vec![Box::new(PerMessageDeflate::new(config, Role::Server))];
+    Extensions::negotiate(candidates, "permessage-deflate; client_max_window_bits")
+}
+
+fn read_frames(client: &mut TcpStream, count: usize) -> Vec<DataFrame> {
+    let mut decoder = FrameDecoder::with_role(Role::Client);
+    decoder.set_reserved_bits(RSV1);
+    let mut frames = Vec::new();
+    let mut buffer = [0; 512];
+    while frames.len() < count {
+        let size = client.read(&mut buffer).unwrap();
+        frames.extend(decoder.feed(&buffer[..size]).expect("Failed to decode"));
+    }
+    frames
+}
+
+#[test]
+fn test_prepared_message_shared_between_connections() {
+    let registry = Registry::new();
+    let (first, mut first_client) = connection_pair(ServerConfig::default(), Extensions::new());
+    let (second, mut second_client) = connection_pair(ServerConfig::default(), deflate(false));
+    registry.insert(&first);
+    registry.insert(&second);
+
+    let prepared = PreparedMessage::new("tick");
+    assert!(!prepared.is_compressed());
+    assert!(registry.broadcast(&prepared).is_empty());
+    assert!(registry.broadcast(prepared.clone()).is_empty());
+
+    // the permessage-deflate connection gets the plain frame too
+    for client in [&mut first_client, &mut second_client] {
+        for frame in read_frames(client, 2) {
+            assert!(!frame.is_rsv1());
+            assert_eq!(frame.payload, b"tick");
+        }
+    }
+}
+
+#[test]
+fn test_compressed_prepared_message() {
+    let config = DeflateConfig {
+        server_no_context_takeover: true,
+        ..DeflateConfig::default()
+    };
+    let text = "price update ".repeat(50);
+    let prepared = PreparedMessage::compressed(text.as_str(), config).unwrap();
+    assert!(prepared.is_compressed());
+    assert_eq!(prepared.message(), &Message::Text(text.clone()));
+
+    let (plain, mut plain_client) = connection_pair(ServerConfig::default(), Extensions::new());
+    let (shared, mut shared_client) = connection_pair(ServerConfig::default(), deflate(true));
+    let (takeover, mut takeover_client) = connection_pair(ServerConfig::default(), deflate(false));
+    for connection in [&plain, &shared, &takeover] {
+        connection.send_prepared(&prepared).unwrap();
+    }
+
+    assert_eq!(read_frames(&mut plain_client, 1)[0].payload, text.as_bytes());
+
+    let frame = read_frames(&mut shared_client, 1).remove(0);
+    assert!(frame.is_rsv1());
+    assert_eq!(frame.get_opcode(), OpCode::Text);
+    assert!(frame.payload.len() < text.len());
+    let inflated = config.decoder(Role::Client).decompress(&frame.payload).unwrap();
+    assert_eq!(inflated, text.as_bytes());
+
+    // context takeover keeps per connection state, so it gets the plain frame
+    let frame = read_frames(&mut takeover_client, 1).remove(0);
+    assert!(!frame.is_rsv1());
+    assert_eq!(frame.payload, text.as_bytes());
+}
+
+#[test]
+fn test_prepared_message_respects_fragment_size() {
+    let config = ServerConfig {
+        fragment_size: Some(4),
+        ..ServerConfig::default()
+    };
+    let (connection, mut client) = connection_pair(config, Extensions::new());
+    connection.send_prepared(&PreparedMessage::new(vec![1u8, 2, 3, 4, 5, 6])).unwrap();
+
+    let frames = read_frames(&mut client, 2);
+    assert!(!frames[0].is_final_fragment());
+    assert_eq!(frames[0].payload, vec![1, 2, 3, 4]);
+    assert_eq!(frames[1].get_opcode(), OpCode::ContinuationFrame);
+    assert_eq!(frames[1].payload, vec![5, 6]);
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use std::io::Read;
+use std::net::TcpStream;
+use std::time::Duration;
+
+use rusty_socket_core::Extensions;
+use rusty_socket_server::{Connection, Registry, RouteMatch, ServerConfig, SsError};
+
+fn connection_pair(path: &str) -> (Connection, TcpStream) {
+    let route = RouteMatch {
+        path: path.to_string(),
+        ..RouteMatch::default()
+    };
+    common::connection_pair(ServerConfig::default(), Extensions::new(), Some("chat"), route)
+}
+
+fn read_text(client: &mut TcpStream) -> String {
+    let mut header = [0; 2];
+    client.read_exact(&mut header).unwrap();
+    assert_eq!(header[0], 0x81);
+
+    let mut payload = vec![0; header[1] as usize];
+    client.read_exact(&mut payload).unwrap();
+    String::from_utf8(payload).unwrap()
+}
+
+fn has_pending(client: &mut TcpStream) -> bool {
+    client.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+    let pending = client.read(&mut [0; 1]).is_ok();
+    client.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+    pending
+}
+
+#[test]
+fn test_registry_tracks_connections_by_id() {
+    let registry = Registry::new();
+    let (first, _first_client) = connection_pair("/a");
+    let (second, _second_client) = connection_pair("/b");
+    assert!(first.id() < second.id());
+    assert!(first != second);
+
+    registry.insert(&second);
+    registry.insert(&first);
+    assert_eq!(registry.len(), 2);
+    assert_eq!(registry.ids(), vec![first.id(), second.id()]);
+    assert!(registry.get(first.id()) == Some(first.clone()));
+
+    let list = registry.list();
+    assert_eq!(list[1].id, second.id());
+    assert_eq!(list[1].path, "/b");
+    assert_eq!(list[1].protocol.as_deref(), Some("chat"));
+    assert_eq!(list[1].peer_addr, second.peer_addr());
+    assert_eq!(list[1].connected_at, second.connected_at());
+
+    assert!(registry.remove(first.id()).is_some());
+    assert!(registry.remove(first.id()).is_none());
+    assert!(!registry.contains(first.id()));
+    assert_eq!(registry.len(), 1);
+}
+
+#[test]
+fn test_registry_targeted_sends() {
+    let registry = Registry::new();
+    let (first, mut first_client) = connection_pair("/");
+    let (second, mut second_client) = connection_pair("/");
+    let (third, mut third_client) = connection_pair("/");
+    for connection in [&first, &second, &third] {
+        registry.insert(connection);
+    }
+
+    registry.send_to(second.id(), "one").unwrap();
+    assert_eq!(read_text(&mut second_client), "one");
+    assert!(!has_pending(&mut first_client));
+
+    let failures = registry.send_to_many(&[first.id(), third.id()], "many");
+    assert!(failures.is_empty());
+    assert_eq!(read_text(&mut first_client), "many");
+    assert_eq!(read_text(&mut third_client), "many");
+    assert!(!has_pending(&mut second_client));
+
+    let failures = registry.broadcast_except(first.id(), "others");
+    assert!(failures.is_empty());
+    assert_eq!(read_text(&mut second_client), "others");
+    assert_eq!(read_text(&mut third_client), "others");
+    assert!(!has_pending(&mut first_client));
+
+    assert!(registry.broadcast("all").is_empty());
+    for client in [&mut first_client, &mut second_client, &mut third_client] {
+        assert_eq!(read_text(client), "all");
+    }
+
+    registry.remove(third.id());
+    assert!(matches!(registry.send_to(third.id(), "gone"), Err(SsError::UnknownConnection)));
+    let failures = registry.send_to_many(&[third.id(), first.id()], "some");
+    assert_eq!(failures.len(), 1);
+    assert!(matches!(failures[0], (id, SsError::UnknownConnection) if id == third.id()));
+    assert_eq!(read_text(&mut first_client), "some");
+}
+
+#[test]
+fn test_registry_rooms() {
+    let registry = Registry::new();
+    let (first, mut first_client) = connection_pair("/");
+    let (second, mut second_client) = connection_pair("/");
+    let (outsider, mut outsider_client) = connection_pair("/");
+    let first = first.with_registry(registry.clone());
+    let second = second.with_registry(registry.clone());
+    for connection in [&first, &second, &outsider] {
+        registry.insert(connection);
+    }
+
+    assert!(first.join("lobby"));
+    assert!(first.join("news"));
+    assert!(second.join("lobby"));
+    assert_eq!(registry.rooms(), vec!["lobby", "news"]);
+    assert_eq!(first.rooms(), vec!["lobby", "news"]);
+    assert_eq!(registry.members("lobby"), vec![first.id(), second.id()]);
+
+    assert!(registry.publish("lobby", "hello").is_empty());
+    assert_eq!(read_text(&mut first_client), "hello");
+    assert_eq!(read_text(&mut second_client), "hello");
+    assert!(!has_pending(&mut outsider_client));
+
+    assert!(registry.publish_except("lobby", first.id(), "reply").is_empty());
+    assert_eq!(read_text(&mut second_client), "reply");
+    assert!(!has_pending(&mut first_client));
+
+    assert!(second.leave("lobby"));
+    assert!(!second.leave("lobby"));
+    assert!(registry.publish("nowhere", "lost").is_empty());
+
+    registry.remove(first.id());
+    assert!(registry.rooms().is_empty());
+    assert!(registry.members("lobby").is_empty());
+    assert!(!first.join("lobby"));
+}
@@ -0,0 +1,112 @@
+use rusty_socket_server::{HandShake, RequestError, RequestLine, RequestReader};
+
+const REQUEST: &str = "GET /chat HTTP/1.1\r\n\
+    Host: localhost:8080\r\n\
+    Upgrade: websocket\r\n\
+    Connection: keep-alive, Upgrade\r\n\
+    Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+    Sec-WebSocket-Version: 13\r\n\
+    \r\n";
+
+fn response_header(handshake: &HandShake, name: &str) -> Option<String> {
+    handshake.response.headers.as_ref().and_then(|headers| headers.get(name).cloned())
+}
+
+#[test]
+fn test_request_reader_collects_head_over_several_reads() {
+    let mut reader = RequestReader::new(8192);
+    let mut data = REQUEST.as_bytes().to_vec();
+    data.extend_from_slice(&[0x81, 0x80]);
+
+    let mut chunks = data.chunks(REQUEST.len() / 3 + 1);
+    assert_eq!(reader.push(chunks.next().unwrap()), Ok(None));
+    assert_eq!(reader.push(chunks.next().unwrap()), Ok(None));
+
+    let (head, rest) = reader.push(chunks.next().unwrap()).unwrap().unwrap();
+    assert_eq!(head, REQUEST);
+    assert_eq!(rest, vec![0x81, 0x80]);
+    assert_eq!(chunks.next(), None);
+}
+
+#[test]
+fn test_request_reader_accepts_bare_newlines() {
+    let mut reader = RequestReader::new(8192);
+    let request = REQUEST.replace("\r\n", "\n");
+
+    let (head, rest) = reader.push(request.as_bytes()).unwrap().unwrap();
+    assert_eq!(head, request);
+    assert!(rest.is_empty());
+    assert!(RequestLine::parse(&head).is_ok());
+}
+
+#[test]
+fn test_request_reader_limits_head_size() {
+    let mut reader = RequestReader::new(64);
+    assert_eq!(reader.push(&[b'a'; 60]), Ok(None));
+    assert_eq!(reader.push(&[b'a'; 10]), Err(RequestError::TooLarge));
+
+    let mut reader = RequestReader::new(64);
+    assert_eq!(reader.push(REQUEST.as_bytes()), Err(RequestError::TooLarge));
+
+    let handshake = HandShake::perform(REQUEST);
+    assert_eq!(handshake.response.status_code, 101);
+    assert_eq!(RequestError::TooLarge.response().status_code, 431);
+}
+
+#[test]
+fn test_request_line_joins_repeated_headers() {
+    let request = REQUEST.replace(
+        "Upgrade: websocket\r\n",
+        "Upgrade:websocket\r\nCookie: a=1\r\ncookie: b=2\r\nX-Forwarded-For: 10.0.0.1\r\nX-Forwarded-For: 10.0.0.2\r\n",
+    );
+    let request = RequestLine::parse(&request).unwrap();
+
+    assert_eq!(request.headers.get("upgrade").unwrap(), "websocket");
+    assert_eq!(request.headers.get("cookie").unwrap(), "a=1; b=2");
+    assert_eq!(request.cookie("b").unwrap(), "2");
+    assert_eq!(request.header_values("X-Forwarded-For"), vec!["10.0.0.1", "10.0.0.2"]);
+    assert_eq!(request.header_values("connection"), vec!["keep-alive", "Upgrade"]);
+    assert!(request.has_token("connection", "upgrade"));
+}
+
+#[test]
+fn test_request_line_rejects_malformed_requests() {
+    let malformed = [
+        REQUEST.replace("GET /chat HTTP/1.1", "GET"),
+        REQUEST.replace("GET /chat HTTP/1.1", "GET /chat"),
+        REQUEST.replace("HTTP/1.1", "HTTP/1.0"),
+        REQUEST.replace("/chat", "chat"),
+        REQUEST.replace("Host: localhost:8080\r\n", ""),
+        REQUEST.replace("Host: localhost:8080", "Host localhost:8080"),
+        REQUEST.replace("Host:", "Bad Name:"),
+        REQUEST.replace("Host: localhost:8080\r\n", "Host: localhost\r\n :8080\r\n"),
+        REQUEST.replace("Connection: keep-alive, Upgrade", "Connection: keep-alive"),
+        REQUEST.replace("Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n", ""),
+        REQUEST.replace("dGhlIHNhbXBsZSBub25jZQ==", "too-short"),
+        String::new(),
+    ];
+
+    for request in malformed {
+        assert!(matches!(RequestLine::parse(&request), Err(RequestError::Malformed(_))), "{:?}", request);
+        assert_eq!(HandShake::perform(&request).response.status_code, 400);
+    }
+}
+
+#[test]
+fn test_handshake_error_responses() {
+    let handshake = HandShake::perform(&REQUEST.replace("GET", "POST"));
+    assert_eq!(handshake.response.status_code, 405);
+    assert_eq!(response_header(&handshake, "Allow").unwrap(), "GET");
+
+    for request in [
+        REQUEST.replace("Sec-WebSocket-Version: 13", "Sec-WebSocket-Version: 8"),
+        REQUEST.replace("Sec-WebSocket-Version: 13\r\n", ""),
+        REQUEST.replace("Upgrade: websocket\r\n", ""),
+        REQUEST.replace("Upgrade: websocket", "Upgrade: h2c"),
+    ] {
+        let handshake = HandShake::perform(&request);
+        assert!(handshake.request.is_none());
+        assert_eq!(handshake.response.status_code, 426);
+        assert_eq!(response_header(&handshake, "Sec-WebSocket-Version").unwrap(), "13");
+    }
+}
@@ -0,0 +1,60 @@
+use rusty_socket_server::router::parse_query;
+use rusty_socket_server::{Connection, Message, Router};
+
+fn ignore(_: &Connection, _: Message) {}
+
+fn router() -> Router {
+    Router::new()
+        .route("/chat/:room", ignore)
+        .route("/chat/:room/users/:user", ignore)
+        .route("/feed/*", ignore)
+        .route("/", ignore)
+}
+
+#[test]
+fn test_router_extracts_path_params() {
+    let (_, route) = router().resolve("/chat/lobby").unwrap();
+    assert_eq!(route.pattern, "/chat/:room");
+    assert_eq!(route.param("room"), Some("lobby"));
+    assert_eq!(route.wildcard, None);
+
+    let (_, route) = router().resolve("/chat/rust%20fans/users/bob/").unwrap();
+    assert_eq!(route.pattern, "/chat/:room/users/:user");
+    assert_eq!(route.param("room"), Some("rust fans"));
+    assert_eq!(route.param("user"), Some("bob"));
+}
+
+#[test]
+fn test_router_wildcard_takes_the_rest() {
+    let (_, route) = router().resolve("/feed/sports/football").unwrap();
+    assert_eq!(route.pattern, "/feed/*");
+    assert_eq!(route.wildcard.as_deref(), Some("sports/football"));
+
+    let (_, route) = router().resolve("/feed").unwrap();
+    assert_eq!(route.wildcard.as_deref(), Some(""));
+}
+
+#[test]
+fn test_router_parses_query() {
+    let (_, route) = router().resolve("/chat/lobby?user=bob&token=a%2Bb&greeting=hi+there&flag#top").unwrap();
+    assert_eq!(route.path, "/chat/lobby");
+    assert_eq!(route.query("user"), Some("bob"));
+    assert_eq!(route.query("token"), Some("a+b"));
+    assert_eq!(route.query("greeting"), Some("hi there"));
+    assert_eq!(route.query("flag"), Some(""));
+
+    assert!(parse_query("").is_empty());
+    assert_eq!(parse_query("a=1&a=2").get("a").map(String::as_str), Some("2"));
+}
+
+#[test]
+fn test_router_unknown_paths() {
+    assert!(router().resolve("/chat").is_none());
+    assert!(router().resolve("/chat/lobby/extra").is_none());
+    assert!(router().resolve("/unknown").is_none());
+    assert!(router().resolve("/").is_some());
+
+    let (_, route) = Router::new().fallback(ignore).resolve("/anything?x=1").unwrap();
+    assert_eq!(route.pattern, "");
+    assert_eq!(route.query("x"), Some("1"));
+}
@@ -0,0 +1,25 @@
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use rusty_socket_server::{BroadcastHandler, SocketServer};
+
+#[test]
+fn test_server_handle_stops_accept_loop() {
+    let server = SocketServer::build("127.0.0.1:0", BroadcastHandler::new()).unwrap();
+    let handle = server.handle();
+    assert!(!handle.is_shutting_down());
+
+    let (done_tx, done_rx) = mpsc::channel();
+    thread::spawn(move || {
+        server.start();
+        done_tx.send(()).unwrap();
+    });
+    thread::sleep(Duration::from_millis(100));
+    assert!(done_rx.try_recv().is_err());
+
+    handle.shutdown();
+    assert!(handle.is_shutting_down());
+    assert_eq!(handle.active_connections(), 0);
+    done_rx.recv_timeout(Duration::from_secs(2)).unwrap();
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use std::io::Read;
+use std::net::TcpStream;
+
+use rusty_socket_core::{ConnectionStatus, DataFrame, Extension, Extensions, Message, RsError, RsResult};
+use rusty_socket_server::{Connection, OverflowPolicy, RouteMatch, ServerConfig, SsError};
+
+const MESSAGE_SIZE: usize = 64 * 1024;
+
+fn connection_pair(policy: OverflowPolicy) -> (Connection, TcpStream) {
+    connection_with_extensions(policy, Extensions::new())
+}
+
+fn connection_with_extensions(policy: OverflowPolicy, extensions: Extensions) -> (Connection, TcpStream) {
+    let config = ServerConfig {
+        write_queue_size: 4,
+        overflow_policy: policy,
+        ..ServerConfig::default()
+    };
+    common::connection_pair(config, extensions, None, RouteMatch::default())
+}
+
+// Message `n` is MESSAGE_SIZE bytes of `n`.
+fn message(n: u8) -> Message {
+    Message::Binary(vec![n; MESSAGE_SIZE])
+}
+
+// Sends until the client's socket buffers and the queue are full.
+fn flood(connection: &Connection, count: u8) -> Vec<Result<(), SsError>> {
+    (0..count).map(|n| connection.send(message(n))).collect()
+}
+
+// Returns the first byte of the next binary message, or the close code.
+fn read_next(client: &mut TcpStream) -> Result<u8, u16> {
+    let mut header = [0; 2];
+    client.read_exact(&mut header).unwrap();
+    let length = match header[1] {
+        127 => {
+            let mut length = [0; 8];
+            client.read_exact(&mut length).unwrap();
+            u64::from_be_bytes(length) as usize
+        }
+        126 => {
+            let mut length = [0; 2];
+            client.read_exact(&mut length).unwrap();
+            u16::from_be_bytes(length) as usize
+        }
+        length => length as usize,
+    };
+    let mut payload = vec![0; length];
+    client.read_exact(&mut payload).unwrap();
+
+    match header[0] {
+        0x82 => Ok(payload[0]),
+        0x88 => Err(u16::from_be_bytes([payload[0], payload[1]])),
+        opcode => panic!("unexpected frame {:#x}", opcode),
+    }
+}
+
+#[test]
+fn test_write_queue_drop_newest() {
+    let (connection, mut client) = connection_pair(OverflowPolicy::DropNewest);
+
+    assert!(flood(&connection, 200).iter().all(Result::is_ok));
+    assert!(connection.dropped_messages() > 0);
+    assert!(connection.queued() <= 4);
+    assert_eq!(connection.info().dropped_messages, connection.dropped_messages());
+
+    // the first messages arrive in order, later ones only made it in whenever
+    // the writer freed a slot
+    let received: Vec<u8> = (0..200 - connection.dropped_messages()).map(|_| read_next(&mut client).unwrap()).collect();
+    assert_eq!(received[..4], [0, 1, 2, 3]);
+    assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_write_queue_drop_oldest() {
+    let (connection, mut client) = connection_pair(OverflowPolicy::DropOldest);
+
+    assert!(flood(&connection, 200).iter().all(Result::is_ok));
+    let dropped = connection.dropped_messages();
+    assert!(dropped > 0);
+
+    // the newest messages survive, older waiting ones made room for them
+    let received: Vec<u8> = (0..200 - dropped).map(|_| read_next(&mut client).unwrap()).collect();
+    assert_eq!(*received.last().unwrap(), 199);
+    assert!(received.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_write_queue_disconnects_slow_consumer() {
+    let (connection, mut client) = connection_pair(OverflowPolicy::Disconnect);
+
+    let results = flood(&connection, 200);
+    let overflow = results.iter().position(Result::is_err).unwrap();
+    assert!(matches!(results[overflow], Err(SsError::QueueFull)));
+    assert!(results[overflow + 1..].iter().all(|result| matches!(result, Err(SsError::ConnectionClosed))));
+    assert_eq!(connection.status(), ConnectionStatus::Closing);
+
+    // what was already on its way arrives, then the close frame
+    let close_code = loop {
+        if let Err(code) = read_next(&mut client) {
+            break code;
+        }
+    };
+    assert_eq!(close_code, 1008);
+}
+
+#[test]
+fn test_write_queue_bounds_control_frames() {
+    let (connection, _client) = connection_pair(OverflowPolicy::DropNewest);
+
+    // the client stops reading, so pings pile up behind the data
+    assert!(flood(&connection, 200).iter().all(Result::is_ok));
+    let dropped = connection.dropped_messages();
+    for _ in 0..100 {
+        assert!(connection.send(Message::Ping(Vec::new())).is_ok());
+    }
+
+    // only data messages count as dropped
+    assert_eq!(connection.dropped_messages(), dropped);
+    assert!(connection.queued() <= 4 + 16);
+}
+
+// Agrees to everything but cannot encode a single message.
+struct Broken;
+
+impl Extension for Broken {
+    fn name(&self) -> &str {
+        "x-broken"
+    }
+
+    fn rsv_bits(&self) -> u8 {
+        0
+    }
+
+    fn offer(&self) -> String {
+        String::from("x-broken")
+    }
+
+    fn negotiate(&mut self, _offer: &str) -> Option<String> {
+        Some(String::from("x-broken"))
+    }
+
+    fn accept(&mut self, _response: &str) -> RsResult<()> {
+        Ok(())
+    }
+
+    fn encode(&mut self, _frames: Vec<DataFrame>) -> RsResult<Vec<DataFrame>> {
+        Err(RsError::CompressionError)
+    }
+
+    fn decode(&mut self, frame: DataFrame) -> RsResult<DataFrame> {
+        Ok(frame)
+    }
+}
+
+#[test]
+fn test_write_queue_fails_connection_on_encode_error() {
+    let extensions = Extensions::negotiate(vec![Box::new(Broken)], "x-broken");
+    let (connection, mut client) = connection_with_extensions(OverflowPolicy::Disconnect, extensions);
+
+    // the message is not silently skipped, the client is told with 1011
+    assert!(connection.send(message(1)).is_ok());
+    assert_eq!(read_next(&mut client), Err(1011));
+    assert_eq!(connection.status(), ConnectionStatus::Closing);
+}